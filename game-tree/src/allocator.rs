@@ -1,34 +1,72 @@
+use std::mem;
 use std::ptr;
 use std::slice;
-use std::sync::Mutex;
 
-use util::arena::{Arena, Error};
+use util::arena::{Error, SharedAllocator};
 use util::volatile::Volatile;
 
 use crate::game::{Event, Game, Parameter, ParameterMapping, Stage};
-use crate::node::{ActionNode, ChanceNode, NodePtr, NodePtrIterator, RootNode};
+use crate::handle::NodeHandle;
+use crate::node::{ActionNode, ChanceNode, NodePtr, RootNode};
+
+// With an allocator that only measures, like `DummyArena`, the functions here write nothing and
+// return null node pointers, or handles to null roots, which have no children.
+
+/// Allocates a lone root node for a tree of `G` grown from `state`, whose children can be added
+/// later, as lazy solvers do.  The handle borrows `arena`, so the tree can't outlive it.
+pub fn allocate_root_node<'a, G, P>(
+    state: G::State,
+    arena: &'a impl SharedAllocator,
+) -> Result<NodeHandle<'a, G, P>, Error>
+where
+    G: Game,
+{
+    let root = allocate_root(arena)?;
+    Ok(unsafe { NodeHandle::new(root, state) })
+}
 
-pub fn allocate_root_node(arena: &Mutex<Arena>) -> Result<NodePtr, Error> {
-    let root_node = {
-        let mut arena = arena.lock().unwrap();
-        arena.allocate::<RootNode>(1)?
+pub(crate) fn allocate_root(arena: &impl SharedAllocator) -> Result<NodePtr, Error> {
+    let root_node = match arena.allocate::<RootNode>(1)? {
+        Some(root_node) => root_node,
+        None => return Ok(null()),
     };
 
     unsafe {
         (*root_node).as_mut_ptr().write(RootNode {
             _pad: ptr::null_mut(),
-            first_child: Volatile::new(NodePtr::new::<()>(ptr::null_mut())),
+            first_child: Volatile::new(null()),
         });
     }
 
     Ok(NodePtr::new(root_node))
 }
 
+/// Returns the root node allocated as an arena's first allocation, such as in an arena reopened
+/// with `Arena::open_file`.  Panics if nothing has been allocated.
+///
+/// # Safety
+///
+/// The arena's first allocation must be the root node of a tree allocated for `G` and `P` from
+/// `state`, by `allocate_tree`, `allocate_root_node` or `load_tree`.
+pub unsafe fn find_root_node<'a, G, P>(
+    state: G::State,
+    arena: &'a impl SharedAllocator,
+) -> NodeHandle<'a, G, P>
+where
+    G: Game,
+{
+    assert!(arena.len() > 0, "arena is empty");
+
+    let base = arena.base();
+    let root = NodePtr::new(base.wrapping_add(base.align_offset(mem::align_of::<RootNode>())));
+    NodeHandle::new(root, state)
+}
+
 /// Panics if `actions` is empty, or if `parameter_count` is 0.
 pub fn allocate_action_nodes<G, P>(
     actions: &[G::Action],
     parameter_count: usize,
-    arena: &Mutex<Arena>,
+    arena: &impl SharedAllocator,
 ) -> Result<NodePtr, Error>
 where
     G: Game,
@@ -45,12 +83,11 @@ where
 
     let total_parameter_count = actions.len() * parameter_count;
 
-    let (mut current_node, parameters) = {
-        let mut arena = arena.lock().unwrap();
-        (
-            arena.allocate::<ActionNode<G::Action, P>>(actions.len())?,
-            arena.allocate::<P>(total_parameter_count)?,
-        )
+    let (mut current_node, parameters) = match arena
+        .allocate_pair::<ActionNode<G::Action, P>, P>(actions.len(), total_parameter_count)?
+    {
+        Some(allocation) => allocation,
+        None => return Ok(null()),
     };
 
     // Fill new nodes from the back of the memory block forward.
@@ -80,7 +117,7 @@ where
             action,
             parameters,
             next_sibling: last_node,
-            first_child: Volatile::new(NodePtr::new::<()>(ptr::null_mut())),
+            first_child: Volatile::new(null()),
         };
 
         last_node = current_node as *mut ActionNode<G::Action, P>;
@@ -95,14 +132,14 @@ where
 
 pub fn allocate_chance_nodes<G>(
     chances: &[G::Chance],
-    arena: &Mutex<Arena>,
+    arena: &impl SharedAllocator,
 ) -> Result<NodePtr, Error>
 where
     G: Game,
 {
-    let mut current_node = {
-        let mut arena = arena.lock().unwrap();
-        arena.allocate::<ChanceNode<G::Chance>>(chances.len())?
+    let mut current_node = match arena.allocate::<ChanceNode<G::Chance>>(chances.len())? {
+        Some(nodes) => nodes,
+        None => return Ok(null()),
     };
 
     // Fill new nodes from the back of the memory block forward.
@@ -121,7 +158,7 @@ where
         let node = ChanceNode {
             result: chance,
             next_sibling: last_node,
-            first_child: Volatile::new(NodePtr::new::<()>(ptr::null_mut())),
+            first_child: Volatile::new(null()),
         };
 
         last_node = current_node as *mut ChanceNode<G::Chance>;
@@ -134,97 +171,199 @@ where
     Ok(NodePtr::new(last_node))
 }
 
-pub fn allocate_tree<G, P>(root_state: &G::State, arena: &Mutex<Arena>) -> Result<NodePtr, Error>
+/// Returns `node`'s first child, allocating all of its children first if it doesn't have any.
+/// `state` is the state at `node`, and must not be terminal.
+///
+/// Other threads may be reading or expanding the node at the same time.  If two threads expand
+/// it at once, both allocate children, but only one set is published, and the other is wasted.
+pub fn expand_node<G, P>(
+    node: NodePtr,
+    state: &G::State,
+    arena: &impl SharedAllocator,
+) -> Result<NodePtr, Error>
 where
     G: Game,
     P: Parameter,
 {
-    let root_node = allocate_root_node(arena)?;
+    if let Some(first_child) = node.children().next() {
+        return Ok(first_child);
+    }
+
+    assert!(
+        !G::get_stage(state).is_terminal(),
+        "cannot expand a terminal node"
+    );
+
+    let mut events_buffer = Vec::new();
+    let first_child = allocate_event_nodes::<G, P>(state, &mut events_buffer, arena)?;
+
+    Ok(node.publish_children(first_child))
+}
+
+/// Allocates every node of the tree below `root_state`.  The handle borrows `arena`, so the tree
+/// can't outlive it.
+pub fn allocate_tree<'a, G, P>(
+    root_state: &G::State,
+    arena: &'a impl SharedAllocator,
+) -> Result<NodeHandle<'a, G, P>, Error>
+where
+    G: Game,
+    P: Parameter,
+{
+    let root = build_tree::<G, P>(root_state, arena, &mut |_, _, _| {})?;
+    Ok(unsafe { NodeHandle::new(root, root_state.clone()) })
+}
+
+/// Allocates a complete tree.  `visit` is called with every state in the game, terminal or not,
+/// along with its depth, starting from 0 at the root, and the number of events available at it.
+pub(crate) fn build_tree<G, P>(
+    root_state: &G::State,
+    arena: &impl SharedAllocator,
+    visit: &mut impl FnMut(&G::State, usize, usize),
+) -> Result<NodePtr, Error>
+where
+    G: Game,
+    P: Parameter,
+{
+    let root_node = allocate_root(arena)?;
 
     fn allocate_children<G, P>(
         state: &G::State,
+        depth: usize,
         events_buffer: &mut Vec<Event<G::Action, G::Chance>>,
-        arena: &Mutex<Arena>,
+        arena: &impl SharedAllocator,
+        visit: &mut impl FnMut(&G::State, usize, usize),
     ) -> Result<NodePtr, Error>
     where
         G: Game,
         P: Parameter,
     {
-        let stage = G::get_stage(state);
-
-        events_buffer.clear();
-        G::populate_events(state, events_buffer);
-
-        let first_child = if stage.is_action() {
-            let actions = {
-                events_buffer
-                    .iter()
-                    .map(|&e| match e {
-                        Event::Action(a) => a,
-                        _ => panic!("not an action event"),
-                    })
-                    .collect::<Vec<_>>()
-            };
-
-            allocate_action_nodes::<G, P>(
-                &actions,
-                G::ParameterMapping::get_parameter_count(state),
-                arena,
-            )?
-        } else {
-            let chances = {
-                events_buffer
-                    .iter()
-                    .map(|e| match e {
-                        Event::Chance(c) => *c,
-                        _ => panic!("not a chance event"),
-                    })
-                    .collect::<Vec<_>>()
-            };
-
-            allocate_chance_nodes::<G>(&chances, arena)?
-        };
+        let first_child = allocate_event_nodes::<G, P>(state, events_buffer, arena)?;
+
+        visit(state, depth, events_buffer.len());
 
         let mut next_events_buffer = Vec::new();
+        let mut next_parent = first_child;
 
-        for (&e, next_parent) in events_buffer.iter().zip(NodePtrIterator::new(first_child)) {
+        for &e in events_buffer.iter() {
             let mut next_state = state.clone();
             G::advance_state(&mut next_state, e);
 
-            if G::get_stage(&next_state).is_terminal() {
-                continue;
+            if !G::get_stage(&next_state).is_terminal() {
+                let next_first_child = allocate_children::<G, P>(
+                    &next_state,
+                    depth + 1,
+                    &mut next_events_buffer,
+                    arena,
+                    visit,
+                )?;
+
+                if !next_parent.0.is_null() {
+                    next_parent.add_child(next_first_child);
+                }
+            } else {
+                visit(&next_state, depth + 1, 0);
             }
 
-            let next_first_child =
-                allocate_children::<G, P>(&next_state, &mut next_events_buffer, arena)?;
-
-            next_parent.add_child(next_first_child);
+            if !next_parent.0.is_null() {
+                next_parent = next_parent.next_sibling().unwrap_or_else(null);
+            }
         }
 
         Ok(first_child)
     }
 
-    let mut events_buffer = Vec::new();
-    let first_child = allocate_children::<G, P>(root_state, &mut events_buffer, arena)?;
-    root_node.add_child(first_child);
+    if !G::get_stage(root_state).is_terminal() {
+        let mut events_buffer = Vec::new();
+        let first_child =
+            allocate_children::<G, P>(root_state, 0, &mut events_buffer, arena, visit)?;
+
+        if !root_node.0.is_null() {
+            root_node.add_child(first_child);
+        }
+    } else {
+        visit(root_state, 0, 0);
+    }
 
     Ok(root_node)
 }
 
+/// Allocates a node for each of the events available at `state`.
+fn allocate_event_nodes<G, P>(
+    state: &G::State,
+    events_buffer: &mut Vec<Event<G::Action, G::Chance>>,
+    arena: &impl SharedAllocator,
+) -> Result<NodePtr, Error>
+where
+    G: Game,
+    P: Parameter,
+{
+    let stage = G::get_stage(state);
+
+    populate_tree_events::<G>(state, events_buffer);
+
+    if stage.is_action() {
+        let actions = {
+            events_buffer
+                .iter()
+                .map(|&e| match e {
+                    Event::Action(a) => a,
+                    _ => panic!("not an action event"),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let parameter_count = G::ParameterMapping::get_parameter_count(state);
+
+        allocate_action_nodes::<G, P>(&actions, parameter_count, arena)
+    } else {
+        let chances = {
+            events_buffer
+                .iter()
+                .map(|e| match e {
+                    Event::Chance(c) => *c,
+                    _ => panic!("not a chance event"),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        allocate_chance_nodes::<G>(&chances, arena)
+    }
+}
+
+/// Fills `events` with the events that have their own child in the tree.  That's every event,
+/// except at private chance stages, where the first event stands for all of them.
+pub fn populate_tree_events<G>(state: &G::State, events: &mut Vec<Event<G::Action, G::Chance>>)
+where
+    G: Game,
+{
+    events.clear();
+    G::populate_events(state, events);
+
+    if G::get_stage(state).is_private_chance() {
+        events.truncate(1);
+    }
+}
+
+fn null() -> NodePtr {
+    NodePtr::new::<()>(ptr::null_mut())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use std::mem;
+    use std::sync::Mutex;
+
+    use util::arena::{Allocator, Arena, AtomicArena, DummyArena};
 
     use crate::dummy::*;
     use crate::{Game, NodePtr, ParameterMapping};
 
     fn assert_valid_siblings<N>(nodes: &[N]) {
         for i in 0..nodes.len() - 1 {
-            if let Some(sibling) =
-                NodePtr::new::<()>(unsafe { mem::transmute(&nodes[i]) }).next_sibling()
-            {
+            if let Some(sibling) = NodePtr::new(&nodes[i] as *const N as *mut N).next_sibling() {
                 assert_eq!(
                     sibling.0 as *const N,
                     &nodes[i + 1] as *const N,
@@ -312,4 +451,41 @@ mod tests {
 
         assert_valid_siblings(slice);
     }
+
+    #[test]
+    fn test_find_root_node() {
+        let arena = Mutex::new(Arena::with_capacity(200));
+        let root = allocate_root_node::<X, P>(X, &arena).unwrap().ptr();
+        allocate_chance_nodes::<X>(&[[1; 6], [2; 6]], &arena).unwrap();
+        assert_eq!(unsafe { find_root_node::<X, P>(X, &arena) }.ptr().0, root.0);
+
+        let arena = AtomicArena::new(Arena::with_capacity(200));
+        let root = allocate_root_node::<X, P>(X, &arena).unwrap().ptr();
+        allocate_chance_nodes::<X>(&[[1; 6], [2; 6]], &arena).unwrap();
+        assert_eq!(unsafe { find_root_node::<X, P>(X, &arena) }.ptr().0, root.0);
+    }
+
+    #[test]
+    fn test_allocate_dummy() {
+        let arena = Mutex::new(Arena::with_capacity(200));
+        let dummy = Mutex::new(DummyArena::infinite());
+
+        let events = [[1; 6], [2; 6], [3; 6]];
+        let parameter_count = <X as Game>::ParameterMapping::get_parameter_count(&X);
+
+        allocate_action_nodes::<X, P>(&events, parameter_count, &arena).unwrap();
+        allocate_chance_nodes::<X>(&events, &arena).unwrap();
+
+        let ptr = allocate_action_nodes::<X, P>(&events, parameter_count, &dummy).unwrap();
+        assert!(
+            ptr.0.is_null(),
+            "measuring allocators shouldn't return nodes"
+        );
+        allocate_chance_nodes::<X>(&events, &dummy).unwrap();
+
+        // The real arena's buffer may be aligned differently, so allow for an offset.
+        let len = Allocator::len(&*arena.lock().unwrap());
+        let estimate = Allocator::len(&*dummy.lock().unwrap());
+        assert!(len >= estimate && len < estimate + mem::align_of::<ActionNode<[u8; 6], P>>());
+    }
 }
@@ -0,0 +1,767 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::panic::{self, AssertUnwindSafe};
+
+use rand::{Rng, SeedableRng};
+
+use util::rng::JKiss32Rng;
+
+use crate::game::{Event, Game, ParameterMapping, Stage};
+
+/// A broken contract of `Game` or `ParameterMapping`, found at the state reached by `history`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation<A, C> {
+    /// The events leading from the root state, including private chance events.
+    pub history: Vec<Event<A, C>>,
+    pub kind: ViolationKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ViolationKind {
+    /// The stage isn't exactly one of action, chance and terminal.
+    Stage,
+    /// `player_to_act` returned this for an action stage, where it should be below the number of
+    /// players, or for a stage that isn't an action stage, where it should be `None`.
+    Player(Option<usize>),
+    /// `get_branching_hint` returned this, where it should be 0 for terminal states and more than
+    /// 0 for the rest.
+    BranchingHint(usize),
+    /// A non-terminal state has no events.
+    NoEvents,
+    /// `populate_events` produced both actions and chance events.
+    MixedEvents,
+    /// `populate_events` produced actions at a chance stage, or chance events at an action stage.
+    EventKind,
+    /// `format_event` wrote this token for an event, but it's empty, contains whitespace or '/',
+    /// or `parse_event` doesn't parse it back into the same event.
+    EventToken(String),
+    /// A chance event has a negative or non-finite weight.
+    ChanceWeight(f32),
+    /// The chance weights sum to this instead of 1.
+    ChanceWeights(f32),
+    /// `sample_chance` returned an event that isn't at the index it returned, or can't happen.
+    SampledChance(usize),
+    /// The terminal utilities weren't all written, or aren't finite.  Utilities that weren't
+    /// written, or are NaN, are `None`.
+    Utilities(Vec<Option<f32>>),
+    /// The parameter index isn't below the parameter count.
+    ParameterIndex { index: usize, count: usize },
+    /// The state shares its public history with another state, but not its stage, events or
+    /// parameter count, so the two can't share a node.
+    PublicHistory,
+    /// The state's parameter index differs from another state's in the same information set.
+    InformationSet { index: usize, other_index: usize },
+    /// One of the game's functions panicked.
+    Panic(String),
+}
+
+impl<A, C> fmt::Display for Violation<A, C>
+where
+    A: fmt::Debug,
+    C: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+
+        if self.history.is_empty() {
+            write!(f, " at the root")
+        } else {
+            write!(f, " after")?;
+            for event in &self.history {
+                write!(f, " {:?}", event)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+impl fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ViolationKind::Stage => {
+                write!(f, "stage isn't exactly one of action, chance and terminal")
+            }
+            ViolationKind::Player(player) => write!(f, "stage has player to act {:?}", player),
+            ViolationKind::BranchingHint(hint) => {
+                write!(f, "branching hint {} disagrees with the stage", hint)
+            }
+            ViolationKind::NoEvents => write!(f, "non-terminal state has no events"),
+            ViolationKind::MixedEvents => write!(f, "events are both actions and chance"),
+            ViolationKind::EventKind => write!(f, "events are of the wrong kind for the stage"),
+            ViolationKind::EventToken(token) => {
+                write!(f, "event token {:?} doesn't parse back to its event", token)
+            }
+            ViolationKind::ChanceWeight(weight) => write!(f, "invalid chance weight {}", weight),
+            ViolationKind::ChanceWeights(sum) => write!(f, "chance weights sum to {}", sum),
+            ViolationKind::SampledChance(index) => {
+                write!(f, "sampled chance event doesn't match index {}", index)
+            }
+            ViolationKind::Utilities(utilities) => {
+                write!(f, "invalid terminal utilities [")?;
+                for (i, utility) in utilities.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    match utility {
+                        Some(utility) => write!(f, "{:?}", utility)?,
+                        None => write!(f, "?")?,
+                    }
+                }
+                write!(f, "]")
+            }
+            ViolationKind::ParameterIndex { index, count } => {
+                write!(
+                    f,
+                    "parameter index {} isn't below the count {}",
+                    index, count
+                )
+            }
+            ViolationKind::PublicHistory => {
+                write!(f, "state differs from others with the same public history")
+            }
+            ViolationKind::InformationSet { index, other_index } => write!(
+                f,
+                "parameter index {} differs from {} in the same information set",
+                index, other_index
+            ),
+            ViolationKind::Panic(message) => write!(f, "panicked: {}", message),
+        }
+    }
+}
+
+/// Walks every state reachable from `root_states`, and returns every violation found.
+///
+/// `root_states` must share a public history, differing only in private information, like each
+/// way of dealing the players' cards, and every state with the same public history below them is
+/// checked against the others.  `private` returns the private information of a player in a state,
+/// and acting states with the same public history and the same private information for the acting
+/// player must have the same parameter index.
+pub fn check_game<G, K, F, const N: usize>(
+    root_states: &[G::State],
+    private: F,
+) -> Vec<Violation<G::Action, G::Chance>>
+where
+    G: Game,
+    K: Eq + Hash,
+    F: Fn(&G::State, usize) -> K,
+{
+    #[allow(clippy::type_complexity)]
+    fn walk<G, K, F, const N: usize>(
+        states: Vec<(G::State, Vec<Event<G::Action, G::Chance>>)>,
+        private: &F,
+        rng: &mut JKiss32Rng,
+        violations: &mut Vec<Violation<G::Action, G::Chance>>,
+    ) where
+        G: Game,
+        K: Eq + Hash,
+        F: Fn(&G::State, usize) -> K,
+    {
+        let mut shape = None;
+        let mut indices = HashMap::new();
+        let mut next_states: Vec<Vec<_>> = Vec::new();
+
+        for (state, history) in states {
+            let checked = match check_state::<G, N>(&state, &history, rng, violations) {
+                Some(checked) => checked,
+                None => continue,
+            };
+
+            match &shape {
+                None => shape = Some(checked.shape.clone()),
+                Some(shape) if *shape != checked.shape => {
+                    report(violations, &history, ViolationKind::PublicHistory);
+                    continue;
+                }
+                _ => (),
+            }
+
+            if let (Some(player), Some(index)) = (checked.shape.player, checked.index) {
+                let other_index = *indices.entry(private(&state, player)).or_insert(index);
+                if other_index != index {
+                    report(
+                        violations,
+                        &history,
+                        ViolationKind::InformationSet { index, other_index },
+                    );
+                }
+            }
+
+            // Every event of a private chance stage leads to the same public history.
+            let children = if checked.private {
+                1
+            } else {
+                checked.events.len()
+            };
+            next_states.resize_with(children, Vec::new);
+
+            for (i, &event) in checked.events.iter().enumerate() {
+                let mut next_state = state.clone();
+                G::advance_state(&mut next_state, event);
+
+                let mut next_history = history.clone();
+                next_history.push(event);
+
+                next_states[if checked.private { 0 } else { i }].push((next_state, next_history));
+            }
+        }
+
+        for states in next_states {
+            walk::<G, K, F, N>(states, private, rng, violations);
+        }
+    }
+
+    let mut violations = Vec::new();
+
+    walk::<G, K, F, N>(
+        root_states
+            .iter()
+            .map(|state| (state.clone(), Vec::new()))
+            .collect(),
+        &private,
+        &mut JKiss32Rng::seed_from_u64(0),
+        &mut violations,
+    );
+
+    violations
+}
+
+/// Plays `playouts` games from states made by `root_state`, choosing actions uniformly and
+/// sampling chance events with `Game::sample_chance`, and returns every violation found.  This is
+/// for games too large to walk with `check_game`.
+///
+/// States with the same public history are checked against each other as they're met, the same
+/// way `check_game` does, so `root_state` must always make states with the same public history.
+pub fn sample_game<G, K, F, R, S, const N: usize>(
+    mut root_state: S,
+    playouts: usize,
+    rng: &mut R,
+    private: F,
+) -> Vec<Violation<G::Action, G::Chance>>
+where
+    G: Game,
+    K: Eq + Hash,
+    F: Fn(&G::State, usize) -> K,
+    R: Rng,
+    S: FnMut(&mut R) -> G::State,
+{
+    let mut violations = Vec::new();
+
+    // Public histories are keyed by their formatting, since events can't be hashed.
+    let mut shapes = HashMap::new();
+    let mut indices = HashMap::new();
+
+    let mut chance_rng = JKiss32Rng::seed_from_u64(rng.gen());
+
+    for _ in 0..playouts {
+        let mut state = root_state(rng);
+        let mut history = Vec::new();
+        let mut public_history = String::new();
+
+        while let Some(checked) =
+            check_state::<G, N>(&state, &history, &mut chance_rng, &mut violations)
+        {
+            let shape = shapes
+                .entry(public_history.clone())
+                .or_insert_with(|| checked.shape.clone());
+            if *shape != checked.shape {
+                report(&mut violations, &history, ViolationKind::PublicHistory);
+                break;
+            }
+
+            if let (Some(player), Some(index)) = (checked.shape.player, checked.index) {
+                let other_index = *indices
+                    .entry((public_history.clone(), private(&state, player)))
+                    .or_insert(index);
+                if other_index != index {
+                    report(
+                        &mut violations,
+                        &history,
+                        ViolationKind::InformationSet { index, other_index },
+                    );
+                }
+            }
+
+            if checked.shape.terminal {
+                break;
+            }
+
+            let event = if checked.shape.chance {
+                let chance =
+                    panic::catch_unwind(AssertUnwindSafe(|| G::sample_chance(&state, rng).0));
+                match chance {
+                    Ok(chance) => Event::Chance(chance),
+                    Err(payload) => {
+                        report(&mut violations, &history, panic_violation(payload));
+                        break;
+                    }
+                }
+            } else {
+                checked.events[rng.gen_range(0..checked.events.len())]
+            };
+
+            if !checked.private {
+                public_history.push_str(&format!("{:?} ", event));
+            }
+
+            G::advance_state(&mut state, event);
+            history.push(event);
+        }
+    }
+
+    violations
+}
+
+/// The parts of a state that must agree across every state with the same public history.
+#[derive(Clone, PartialEq)]
+struct Shape<A, C> {
+    terminal: bool,
+    chance: bool,
+    player: Option<usize>,
+    /// Events of private chance stages are left out, since they can differ.
+    public_events: Vec<Event<A, C>>,
+    parameter_count: Option<usize>,
+}
+
+struct CheckedState<A, C> {
+    shape: Shape<A, C>,
+    events: Vec<Event<A, C>>,
+    private: bool,
+    index: Option<usize>,
+}
+
+/// Checks the contracts of a single state, and returns what's needed to check it against other
+/// states, unless it's too broken to go on from.
+fn check_state<G, const N: usize>(
+    state: &G::State,
+    history: &[Event<G::Action, G::Chance>],
+    rng: &mut JKiss32Rng,
+    violations: &mut Vec<Violation<G::Action, G::Chance>>,
+) -> Option<CheckedState<G::Action, G::Chance>>
+where
+    G: Game,
+{
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut found = Vec::new();
+        let checked = check_contracts::<G, N>(state, rng, &mut found);
+        (checked, found)
+    }));
+
+    match result {
+        Ok((checked, found)) => {
+            for kind in found {
+                report(violations, history, kind);
+            }
+            checked
+        }
+        Err(payload) => {
+            report(violations, history, panic_violation(payload));
+            None
+        }
+    }
+}
+
+fn check_contracts<G, const N: usize>(
+    state: &G::State,
+    rng: &mut JKiss32Rng,
+    found: &mut Vec<ViolationKind>,
+) -> Option<CheckedState<G::Action, G::Chance>>
+where
+    G: Game,
+{
+    let stage = G::get_stage(state);
+
+    let (action, chance, terminal) = (stage.is_action(), stage.is_chance(), stage.is_terminal());
+    if [action, chance, terminal].iter().filter(|&&b| b).count() != 1 {
+        found.push(ViolationKind::Stage);
+        return None;
+    }
+
+    let player = stage.player_to_act();
+    if action != player.is_some_and(|p| p < N) {
+        found.push(ViolationKind::Player(player));
+        return None;
+    }
+
+    let hint = G::get_branching_hint(state);
+    if terminal != (hint == 0) {
+        found.push(ViolationKind::BranchingHint(hint));
+    }
+
+    let mut events = Vec::new();
+
+    if terminal {
+        let mut utilities = [f32::NAN; N];
+        G::get_terminal_utilities(state, &mut utilities);
+        if !utilities.iter().all(|u| u.is_finite()) {
+            found.push(ViolationKind::Utilities(
+                utilities
+                    .iter()
+                    .map(|&u| (!u.is_nan()).then_some(u))
+                    .collect(),
+            ));
+        }
+
+        return Some(CheckedState {
+            shape: Shape {
+                terminal,
+                chance,
+                player,
+                public_events: Vec::new(),
+                parameter_count: None,
+            },
+            events,
+            private: false,
+            index: None,
+        });
+    }
+
+    G::populate_events(state, &mut events);
+
+    if events.is_empty() {
+        found.push(ViolationKind::NoEvents);
+        return None;
+    }
+
+    let actions = events
+        .iter()
+        .filter(|e| matches!(e, Event::Action(_)))
+        .count();
+    if actions != 0 && actions != events.len() {
+        found.push(ViolationKind::MixedEvents);
+        return None;
+    }
+    if action != (actions != 0) {
+        found.push(ViolationKind::EventKind);
+        return None;
+    }
+
+    for &event in &events {
+        let token = G::format_event(event);
+        let valid = !token.is_empty() && !token.contains(|c: char| c == '/' || c.is_whitespace());
+        if !valid || G::parse_event(state, &token) != Some(event) {
+            found.push(ViolationKind::EventToken(token));
+        }
+    }
+
+    let mut parameter_count = None;
+    let mut index = None;
+
+    if action {
+        let count = G::ParameterMapping::get_parameter_count(state);
+        let parameter_index = G::ParameterMapping::get_parameter_index(state);
+        if parameter_index >= count {
+            found.push(ViolationKind::ParameterIndex {
+                index: parameter_index,
+                count,
+            });
+        }
+
+        parameter_count = Some(count);
+        index = Some(parameter_index);
+    } else {
+        let weights = events
+            .iter()
+            .map(|e| match e {
+                Event::Chance(c) => G::get_chance_weight(state, *c),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+
+        for &weight in &weights {
+            if !weight.is_finite() || weight < 0.0 {
+                found.push(ViolationKind::ChanceWeight(weight));
+            }
+        }
+
+        let sum = weights.iter().sum::<f32>();
+        if (sum - 1.0).abs() > 1e-4 {
+            found.push(ViolationKind::ChanceWeights(sum));
+        }
+
+        let (sampled, sampled_index) = G::sample_chance(state, rng);
+        let position = events
+            .iter()
+            .position(|e| matches!(e, Event::Chance(c) if *c == sampled));
+        if position != Some(sampled_index) || weights[sampled_index] == 0.0 {
+            found.push(ViolationKind::SampledChance(sampled_index));
+        }
+    }
+
+    let private = stage.is_private_chance();
+
+    Some(CheckedState {
+        shape: Shape {
+            terminal,
+            chance,
+            player,
+            public_events: if private { Vec::new() } else { events.clone() },
+            parameter_count,
+        },
+        events,
+        private,
+        index,
+    })
+}
+
+fn report<A, C>(violations: &mut Vec<Violation<A, C>>, history: &[Event<A, C>], kind: ViolationKind)
+where
+    A: Clone + PartialEq,
+    C: Clone + PartialEq,
+{
+    let violation = Violation {
+        history: history.to_vec(),
+        kind,
+    };
+
+    if !violations.contains(&violation) {
+        violations.push(violation);
+    }
+}
+
+fn panic_violation(payload: Box<dyn std::any::Any + Send>) -> ViolationKind {
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    };
+
+    ViolationKind::Panic(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Bug {
+        None,
+        ChanceWeights,
+        ParameterIndex,
+        InformationSet,
+        Utilities,
+        Token,
+    }
+
+    /// A coin is flipped, and the first player, who holds a secret, either stops or goes.
+    #[derive(Clone, Copy, Debug)]
+    struct CoinState {
+        secrets: [u8; 2],
+        heads: Option<bool>,
+        acted: bool,
+        bug: Bug,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum CoinAction {
+        Stop,
+        Go,
+    }
+
+    struct CoinStage(Option<Option<usize>>);
+
+    impl Stage for CoinStage {
+        fn is_action(&self) -> bool {
+            matches!(self.0, Some(Some(_)))
+        }
+        fn is_chance(&self) -> bool {
+            matches!(self.0, Some(None))
+        }
+        fn is_terminal(&self) -> bool {
+            self.0.is_none()
+        }
+        fn player_to_act(&self) -> Option<usize> {
+            self.0.flatten()
+        }
+    }
+
+    struct CoinGame;
+
+    impl Game for CoinGame {
+        type Action = CoinAction;
+        type Chance = bool;
+        type ParameterMapping = CoinGame;
+        type Stage = CoinStage;
+        type State = CoinState;
+
+        fn advance_state(state: &mut CoinState, event: Event<CoinAction, bool>) {
+            match event {
+                Event::Chance(heads) => state.heads = Some(heads),
+                Event::Action(_) => state.acted = true,
+            }
+        }
+
+        fn populate_events(state: &CoinState, events: &mut Vec<Event<CoinAction, bool>>) {
+            events.clear();
+            match (state.heads, state.acted) {
+                (None, _) => events.extend([Event::Chance(true), Event::Chance(false)]),
+                (Some(_), false) => events.extend([
+                    Event::Action(CoinAction::Stop),
+                    Event::Action(CoinAction::Go),
+                ]),
+                (Some(_), true) => (),
+            }
+        }
+
+        fn get_chance_weight(state: &CoinState, heads: bool) -> f32 {
+            if state.bug == Bug::ChanceWeights && heads {
+                0.6
+            } else {
+                0.5
+            }
+        }
+
+        fn sample_chance<R: Rng>(_state: &CoinState, rng: &mut R) -> (bool, usize) {
+            let heads = rng.gen::<bool>();
+            (heads, if heads { 0 } else { 1 })
+        }
+
+        fn get_stage(state: &CoinState) -> CoinStage {
+            match (state.heads, state.acted) {
+                (None, _) => CoinStage(Some(None)),
+                (Some(_), false) => CoinStage(Some(Some(0))),
+                (Some(_), true) => CoinStage(None),
+            }
+        }
+
+        fn get_branching_hint(state: &CoinState) -> usize {
+            if state.acted {
+                0
+            } else {
+                2
+            }
+        }
+
+        fn get_terminal_utilities(state: &CoinState, utilities: &mut [f32]) {
+            utilities[0] = 1.0;
+            if state.bug != Bug::Utilities {
+                utilities[1] = -1.0;
+            }
+        }
+
+        fn format_event(event: Event<CoinAction, bool>) -> String {
+            match event {
+                Event::Chance(true) => "h".to_string(),
+                Event::Chance(false) => "t".to_string(),
+                Event::Action(CoinAction::Stop) => "s".to_string(),
+                Event::Action(CoinAction::Go) => "g".to_string(),
+            }
+        }
+
+        fn parse_event(state: &CoinState, token: &str) -> Option<Event<CoinAction, bool>> {
+            let event = match (token, state.heads) {
+                ("h", None) => Event::Chance(true),
+                ("t", None) => Event::Chance(false),
+                ("s", Some(_)) => Event::Action(CoinAction::Stop),
+                // Go is mistaken for Stop.
+                ("g", Some(_)) if state.bug == Bug::Token => Event::Action(CoinAction::Stop),
+                ("g", Some(_)) => Event::Action(CoinAction::Go),
+                _ => return None,
+            };
+
+            (!state.acted).then_some(event)
+        }
+    }
+
+    impl ParameterMapping for CoinGame {
+        type State = CoinState;
+
+        fn get_parameter_count(_state: &CoinState) -> usize {
+            2
+        }
+
+        fn get_parameter_index(state: &CoinState) -> usize {
+            match state.bug {
+                Bug::ParameterIndex => 2,
+                Bug::InformationSet => state.secrets[1] as usize,
+                _ => state.secrets[0] as usize,
+            }
+        }
+
+        fn get_parameter_description(
+            _state: &CoinState,
+            _alternate_index: Option<usize>,
+        ) -> String {
+            String::new()
+        }
+    }
+
+    fn root_states(bug: Bug) -> Vec<CoinState> {
+        [[0, 0], [0, 1], [1, 0], [1, 1]]
+            .iter()
+            .map(|&secrets| CoinState {
+                secrets,
+                heads: None,
+                acted: false,
+                bug,
+            })
+            .collect()
+    }
+
+    fn check(bug: Bug) -> Vec<Violation<CoinAction, bool>> {
+        check_game::<CoinGame, _, _, 2>(&root_states(bug), |s: &CoinState, p| s.secrets[p])
+    }
+
+    fn sample(bug: Bug) -> Vec<Violation<CoinAction, bool>> {
+        let states = root_states(bug);
+        let mut rng = JKiss32Rng::seed_from_u64(0);
+
+        sample_game::<CoinGame, _, _, _, _, 2>(
+            |rng: &mut JKiss32Rng| states[rng.gen_range(0..states.len())],
+            100,
+            &mut rng,
+            |s: &CoinState, p| s.secrets[p],
+        )
+    }
+
+    #[test]
+    fn test_check_game() {
+        assert_eq!(check(Bug::None), []);
+        assert_eq!(sample(Bug::None), []);
+
+        assert_eq!(
+            check(Bug::ChanceWeights),
+            [Violation {
+                history: vec![],
+                kind: ViolationKind::ChanceWeights(1.1),
+            }]
+        );
+
+        let violations = check(Bug::ParameterIndex);
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0].history, [Event::Chance(true)]);
+        assert_eq!(
+            violations[0].kind,
+            ViolationKind::ParameterIndex { index: 2, count: 2 }
+        );
+
+        // The first player can't see the second player's secret.
+        let violations = check(Bug::InformationSet);
+        assert!(violations.contains(&Violation {
+            history: vec![Event::Chance(true)],
+            kind: ViolationKind::InformationSet {
+                index: 1,
+                other_index: 0
+            },
+        }));
+        assert!(sample(Bug::InformationSet)
+            .iter()
+            .all(|v| matches!(v.kind, ViolationKind::InformationSet { .. })));
+
+        let violations = check(Bug::Utilities);
+        assert_eq!(violations.len(), 4);
+        assert_eq!(
+            violations[0].to_string(),
+            "invalid terminal utilities [1.0, ?] after true Stop"
+        );
+
+        let violations = check(Bug::Token);
+        assert_eq!(violations.len(), 2);
+        assert_eq!(
+            violations[0].to_string(),
+            "event token \"g\" doesn't parse back to its event after true"
+        );
+    }
+}
@@ -1,96 +1,344 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::marker::PhantomData;
 use std::mem;
+use std::rc::Rc;
+use std::sync::Mutex;
 
-use util::arena::DummyArena;
+use util::arena::{Allocator, DummyArena};
 
-use crate::game::{Event, Game, ParameterMapping, Stage};
+use crate::allocator::{build_tree, populate_tree_events};
+use crate::game::{Game, Parameter, ParameterMapping, Stage, SubtreeKey};
 use crate::node::{ActionNode, ChanceNode, RootNode};
 
+/// Measures a tree by running the same builder as `allocate_tree` against a `DummyArena`.
 pub struct TreeEstimator<G, P> {
-    action: usize,
-    chance: usize,
-    parameters: usize,
-    arena: DummyArena,
+    statistics: TreeStatistics,
     _marker: PhantomData<(G, P)>,
 }
 
 impl<G, P> TreeEstimator<G, P>
 where
     G: Game,
-    G::State: Clone,
+    P: Parameter,
 {
     pub fn from_root(root_state: G::State) -> Self {
-        let mut estimator = Self {
-            action: 0,
-            chance: 0,
-            parameters: 0,
-            arena: DummyArena::infinite(),
-            _marker: PhantomData,
-        };
+        let arena = Mutex::new(DummyArena::infinite());
+        let mut statistics = TreeStatistics::default();
+
+        build_tree::<G, P>(&root_state, &arena, &mut |state, depth, events| {
+            let counts = state_counts::<G>(state, events);
 
-        estimator.arena.allocate::<RootNode>(1).unwrap();
+            statistics.total += counts;
+            add_counts(&mut statistics.depths, depth, counts);
 
-        let mut events_buffer = Vec::new();
-        estimator.build_tree(root_state, &mut events_buffer);
+            if let Some(player) = G::get_stage(state).player_to_act() {
+                add_counts(&mut statistics.players, player, counts);
+            }
+        })
+        .unwrap();
 
-        estimator
+        let len = Allocator::len(&*arena.lock().unwrap());
+
+        Self::from_statistics(statistics, len)
+    }
+
+    fn from_statistics(mut statistics: TreeStatistics, len: usize) -> Self {
+        let bytes = &mut statistics.bytes;
+        bytes.root_node = mem::size_of::<RootNode>();
+        bytes.action_nodes =
+            statistics.total.action_nodes * mem::size_of::<ActionNode<G::Action, P>>();
+        bytes.chance_nodes =
+            statistics.total.chance_nodes * mem::size_of::<ChanceNode<G::Chance>>();
+        bytes.parameters = statistics.total.parameters * mem::size_of::<P>();
+        bytes.padding =
+            len - bytes.root_node - bytes.action_nodes - bytes.chance_nodes - bytes.parameters;
+
+        statistics.memory_bounds = (len, len + mem::align_of::<RootNode>() - 1);
+
+        Self {
+            statistics,
+            _marker: PhantomData,
+        }
     }
 
     pub fn action_nodes(&self) -> usize {
-        self.action
+        self.statistics.total.action_nodes
     }
 
     pub fn chance_nodes(&self) -> usize {
-        self.chance
+        self.statistics.total.chance_nodes
     }
 
     pub fn parameters(&self) -> usize {
-        self.parameters
+        self.statistics.total.parameters
     }
 
     /// Returns the minimum and maximum number of bytes the tree will occupy in memory.
     /// The maximum is the exact amount + the worst-case alignment offset.
     pub fn memory_bounds(&self) -> (usize, usize) {
-        let max_offset = mem::align_of::<RootNode>() - 1;
-        (self.arena.len(), self.arena.len() + max_offset)
+        self.statistics.memory_bounds
     }
 
-    fn build_tree(
-        &mut self,
-        state: G::State,
-        events_buffer: &mut Vec<Event<G::Action, G::Chance>>,
-    ) {
-        let stage = G::get_stage(&state);
+    pub fn statistics(&self) -> &TreeStatistics {
+        &self.statistics
+    }
+}
+
+impl<G, P> TreeEstimator<G, P>
+where
+    G: SubtreeKey,
+    P: Parameter,
+{
+    /// The same as `from_root`, but the subtree below each key returned by
+    /// `SubtreeKey::get_subtree_key` is only walked once, so games with many isomorphic subtrees,
+    /// like the betting after each possible deal, can be measured without visiting every node.
+    /// The results are exact as long as the keys are.
+    pub fn from_root_memoized(root_state: G::State) -> Self {
+        let mut memo = HashMap::new();
+
+        let root_len = mem::size_of::<RootNode>();
+        let subtree = measure_subtree::<G, P>(&root_state, root_len, &mut memo);
+
+        let mut statistics = TreeStatistics {
+            depths: subtree.depths.clone(),
+            players: subtree.players.clone(),
+            ..Default::default()
+        };
+        for &counts in &subtree.depths {
+            statistics.total += counts;
+        }
+
+        Self::from_statistics(statistics, root_len + subtree.len)
+    }
+}
+
+/// The statistics of the subtree below a state, with depths relative to the state.
+struct Subtree {
+    depths: Vec<NodeCounts>,
+    players: Vec<NodeCounts>,
+    /// The bytes allocated for the subtree, including alignment.
+    len: usize,
+}
+
+/// Measures the subtree below `state`, where `offset` is the number of bytes allocated before it.
+/// How much alignment the subtree needs depends on where it starts, so subtrees are memoized by
+/// their key and their offset modulo the largest alignment.
+fn measure_subtree<G, P>(
+    state: &G::State,
+    offset: usize,
+    memo: &mut HashMap<(G::Key, usize), Rc<Subtree>>,
+) -> Rc<Subtree>
+where
+    G: SubtreeKey,
+    P: Parameter,
+{
+    let max_align = mem::align_of::<ActionNode<G::Action, P>>()
+        .max(mem::align_of::<ChanceNode<G::Chance>>())
+        .max(mem::align_of::<P>());
+
+    let key = G::get_subtree_key(state).map(|key| (key, offset % max_align));
+
+    if let Some(subtree) = key.as_ref().and_then(|key| memo.get(key)) {
+        return subtree.clone();
+    }
+
+    let stage = G::get_stage(state);
+
+    let mut events = Vec::new();
+    populate_tree_events::<G>(state, &mut events);
+
+    // Mirror the allocations made by `allocate_event_nodes`.
+    let mut len = offset;
+    if stage.is_action() {
+        len = bump::<ActionNode<G::Action, P>>(len, events.len());
+        len = bump::<P>(
+            len,
+            events.len() * G::ParameterMapping::get_parameter_count(state),
+        );
+    } else if stage.is_chance() {
+        len = bump::<ChanceNode<G::Chance>>(len, events.len());
+    }
+
+    let counts = state_counts::<G>(state, events.len());
+
+    let mut subtree = Subtree {
+        depths: vec![counts],
+        players: Vec::new(),
+        len: 0,
+    };
+
+    if let Some(player) = stage.player_to_act() {
+        add_counts(&mut subtree.players, player, counts);
+    }
+
+    for &event in &events {
+        let mut next_state = state.clone();
+        G::advance_state(&mut next_state, event);
+
+        let child = measure_subtree::<G, P>(&next_state, len, memo);
 
-        if stage.is_terminal() {
-            return;
+        for (depth, &counts) in child.depths.iter().enumerate() {
+            add_counts(&mut subtree.depths, depth + 1, counts);
         }
+        for (player, &counts) in child.players.iter().enumerate() {
+            add_counts(&mut subtree.players, player, counts);
+        }
+
+        len += child.len;
+    }
+
+    subtree.len = len - offset;
+
+    let subtree = Rc::new(subtree);
+
+    if let Some(key) = key {
+        memo.insert(key, subtree.clone());
+    }
+
+    subtree
+}
+
+/// Returns the length of a `DummyArena` of length `len` after allocating `n` values of `T`.
+fn bump<T>(len: usize, n: usize) -> usize {
+    let align = mem::align_of::<T>();
+    len.next_multiple_of(align) + n * mem::size_of::<T>()
+}
 
-        events_buffer.clear();
-        G::populate_events(&state, events_buffer);
-
-        if stage.is_action() {
-            self.arena
-                .allocate::<ActionNode<G::Action, P>>(events_buffer.len())
-                .unwrap();
-            self.action += events_buffer.len();
-
-            let parameters = events_buffer.len() * G::ParameterMapping::get_parameter_count(&state);
-            self.arena.allocate::<P>(parameters).unwrap();
-            self.parameters += parameters;
-        } else {
-            self.arena
-                .allocate::<ChanceNode<G::Chance>>(events_buffer.len())
-                .unwrap();
-            self.chance += events_buffer.len();
+/// Counts a state with `events` events.
+fn state_counts<G>(state: &G::State, events: usize) -> NodeCounts
+where
+    G: Game,
+{
+    let stage = G::get_stage(state);
+
+    let mut counts = NodeCounts::default();
+    if stage.is_terminal() {
+        counts.terminal_states = 1;
+    } else if stage.is_action() {
+        counts.action_states = 1;
+        counts.action_nodes = events;
+        counts.parameters = events * G::ParameterMapping::get_parameter_count(state);
+    } else {
+        counts.chance_states = 1;
+        counts.chance_nodes = events;
+    }
+
+    counts
+}
+
+fn add_counts(counts: &mut Vec<NodeCounts>, index: usize, other: NodeCounts) {
+    if counts.len() <= index {
+        counts.resize(index + 1, NodeCounts::default());
+    }
+    counts[index] += other;
+}
+
+/// A breakdown of a tree's size, printed as a table by its `Display` implementation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TreeStatistics {
+    pub total: NodeCounts,
+    /// The states at each depth, starting from the root at 0, and the nodes allocated for their
+    /// events.
+    pub depths: Vec<NodeCounts>,
+    /// The states where each player acts, and the nodes allocated for their actions.
+    pub players: Vec<NodeCounts>,
+    pub bytes: ByteCounts,
+    /// The same as `TreeEstimator::memory_bounds`.
+    pub memory_bounds: (usize, usize),
+}
+
+impl TreeStatistics {
+    /// The depth of the deepest state.
+    pub fn max_depth(&self) -> usize {
+        self.depths.len().saturating_sub(1)
+    }
+}
+
+/// Counts of the states in part of a game, by their stage, and of the nodes allocated for the
+/// events at them.  Nodes are counted with the state whose events they hold, not with the states
+/// they lead to.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NodeCounts {
+    pub action_states: usize,
+    pub chance_states: usize,
+    pub terminal_states: usize,
+    pub action_nodes: usize,
+    pub chance_nodes: usize,
+    pub parameters: usize,
+}
+
+impl std::ops::AddAssign for NodeCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.action_states += other.action_states;
+        self.chance_states += other.chance_states;
+        self.terminal_states += other.terminal_states;
+        self.action_nodes += other.action_nodes;
+        self.chance_nodes += other.chance_nodes;
+        self.parameters += other.parameters;
+    }
+}
+
+/// The bytes a tree takes up in an arena, by what they hold.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ByteCounts {
+    pub root_node: usize,
+    pub action_nodes: usize,
+    pub chance_nodes: usize,
+    pub parameters: usize,
+    /// Bytes skipped to align allocations, assuming the arena's buffer is aligned for any node.
+    pub padding: usize,
+}
+
+impl ByteCounts {
+    pub fn total(&self) -> usize {
+        self.root_node + self.action_nodes + self.chance_nodes + self.parameters + self.padding
+    }
+}
+
+impl fmt::Display for TreeStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn row(f: &mut fmt::Formatter<'_>, label: &str, counts: &NodeCounts) -> fmt::Result {
+            writeln!(
+                f,
+                "{:<10}{:>14}{:>14}{:>14}{:>14}{:>14}{:>14}",
+                label,
+                counts.action_states,
+                counts.chance_states,
+                counts.terminal_states,
+                counts.action_nodes,
+                counts.chance_nodes,
+                counts.parameters,
+            )
         }
 
-        let mut next_events_buffer = Vec::new();
+        writeln!(
+            f,
+            "{:<10}{:>14}{:>14}{:>14}{:>14}{:>14}{:>14}",
+            "", "action", "chance", "terminal", "action nodes", "chance nodes", "parameters",
+        )?;
+
+        for (depth, counts) in self.depths.iter().enumerate() {
+            row(f, &format!("depth {}", depth), counts)?;
+        }
 
-        for &mut e in events_buffer {
-            let mut next_state = state.clone();
-            G::advance_state(&mut next_state, e);
-            self.build_tree(next_state, &mut next_events_buffer);
+        for (player, counts) in self.players.iter().enumerate() {
+            row(f, &format!("player {}", player), counts)?;
         }
+
+        row(f, "total", &self.total)?;
+
+        writeln!(f)?;
+        writeln!(f, "max depth     {:>14}", self.max_depth())?;
+        writeln!(f, "root node     {:>14} bytes", self.bytes.root_node)?;
+        writeln!(f, "action nodes  {:>14} bytes", self.bytes.action_nodes)?;
+        writeln!(f, "chance nodes  {:>14} bytes", self.bytes.chance_nodes)?;
+        writeln!(f, "parameters    {:>14} bytes", self.bytes.parameters)?;
+        writeln!(f, "padding       {:>14} bytes", self.bytes.padding)?;
+        write!(
+            f,
+            "memory        {:>14} bytes, up to {}",
+            self.memory_bounds.0, self.memory_bounds.1
+        )
     }
 }
@@ -1,4 +1,5 @@
 use std::fmt;
+use std::hash::Hash;
 use std::mem::MaybeUninit;
 
 use rand::Rng;
@@ -29,6 +30,37 @@ pub trait Game {
     fn get_branching_hint(state: &Self::State) -> usize;
 
     fn get_terminal_utilities(state: &Self::State, utilities: &mut [f32]);
+
+    /// Writes `event` as a short token for history strings, like "b" or "r250".  Tokens can't
+    /// contain whitespace or '/', and the events of a state must all have different tokens.  The
+    /// default uses the event's `Debug` formatting.
+    fn format_event(event: Event<Self::Action, Self::Chance>) -> String {
+        format!("{:?}", event)
+    }
+
+    /// Parses a token written by `format_event` into one of the events of `state`, or returns
+    /// `None` if it isn't one.  The default looks for the event among the state's events.
+    fn parse_event(state: &Self::State, token: &str) -> Option<Event<Self::Action, Self::Chance>> {
+        if Self::get_stage(state).is_terminal() {
+            return None;
+        }
+
+        let mut events = Vec::new();
+        Self::populate_events(state, &mut events);
+
+        events.into_iter().find(|&e| Self::format_event(e) == token)
+    }
+}
+
+/// Identifies states whose subtrees have the same shape, so that
+/// `TreeEstimator::from_root_memoized` only has to walk each shape once.
+pub trait SubtreeKey: Game {
+    type Key: Eq + Hash;
+
+    /// States with equal keys must have the same number of events, of the same kind, with the same
+    /// parameter counts and players to act, all the way down their subtrees.  States that can't be
+    /// keyed cheaply can return `None`, and are walked every time.
+    fn get_subtree_key(state: &Self::State) -> Option<Self::Key>;
 }
 
 pub trait ParameterMapping {
@@ -47,6 +79,13 @@ pub trait Stage {
     fn is_chance(&self) -> bool;
     fn is_terminal(&self) -> bool;
 
+    /// Whether this is a chance stage whose events are private information, like the deal of the
+    /// players' cards.  Its events don't change the public history, so the tree gives the stage a
+    /// single child that all of them share, and solvers walk every event through it.
+    fn is_private_chance(&self) -> bool {
+        false
+    }
+
     fn player_to_act(&self) -> Option<usize>;
 }
 
@@ -56,7 +95,7 @@ pub trait Parameter {
         Self: Sized;
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub enum Event<A, C> {
     Action(A),
     Chance(C),
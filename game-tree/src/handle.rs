@@ -0,0 +1,445 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::ptr;
+use std::slice;
+
+use crate::game::{Event, Game, ParameterMapping, Stage};
+use crate::node::{ActionNode, ChanceNode, NodePtr, NodePtrIterator};
+
+/// A node of a tree allocated for `G` with parameters `P`, along with the state at the node.
+///
+/// The state's stage says what kind of children the node has, so keeping the two together lets
+/// the children be read without `unsafe`.  Roots are handed out by the functions that allocate or
+/// load a tree, which borrow its allocator for `'a`, and every other handle is found by following
+/// the children of a root, so no handle outlives the tree.
+pub struct NodeHandle<'a, G, P>
+where
+    G: Game,
+{
+    node: NodePtr,
+    state: G::State,
+    _marker: PhantomData<ActionRef<'a, G::Action, P>>,
+}
+
+impl<'a, G, P> Clone for NodeHandle<'a, G, P>
+where
+    G: Game,
+{
+    fn clone(&self) -> Self {
+        Self {
+            node: self.node,
+            state: self.state.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, G, P> NodeHandle<'a, G, P>
+where
+    G: Game,
+{
+    /// # Safety
+    ///
+    /// `node` must be the root node of a tree allocated for `G` and `P` from `state`, or the node
+    /// reached from such a root by the events that led to `state`, and the tree must live for
+    /// `'a`.
+    pub(crate) unsafe fn new(node: NodePtr, state: G::State) -> Self {
+        Self {
+            node,
+            state,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The same node, reached with `state` instead, such as another deal of the cards.
+    ///
+    /// # Safety
+    ///
+    /// The tree below `state` must be the same as the tree below the handle's state: the same
+    /// kinds of stages and the same numbers of events and parameters everywhere.  That holds when
+    /// the two differ only in private information that the game's events don't depend on.
+    pub unsafe fn with_state(&self, state: G::State) -> Self {
+        Self::new(self.node, state)
+    }
+
+    pub fn ptr(&self) -> NodePtr {
+        self.node
+    }
+
+    pub fn state(&self) -> &G::State {
+        &self.state
+    }
+
+    pub fn into_state(self) -> G::State {
+        self.state
+    }
+
+    pub fn stage(&self) -> G::Stage {
+        G::get_stage(&self.state)
+    }
+
+    /// Whether the node has children yet.  Nodes of trees that are grown lazily might not.
+    pub fn is_expanded(&self) -> bool {
+        self.node.children().next().is_some()
+    }
+
+    /// The action nodes below this node, in the order `Game::populate_events` produces them.
+    /// There are none if the state isn't an action stage.
+    pub fn actions(&self) -> Actions<'a, G::Action, P> {
+        let (children, parameter_count) = if self.stage().is_action() {
+            (
+                self.node.children(),
+                G::ParameterMapping::get_parameter_count(&self.state),
+            )
+        } else {
+            (NodePtrIterator::new(NodePtr::new::<()>(ptr::null_mut())), 0)
+        };
+
+        Actions {
+            children,
+            parent: self.node,
+            parameter_count,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The chance nodes below this node, in the order `Game::populate_events` produces them.
+    /// There are none if the state isn't a chance stage, and one if it's a private chance stage.
+    pub fn chances(&self) -> Chances<'a, G::Chance> {
+        let children = if self.stage().is_chance() {
+            self.node.children()
+        } else {
+            NodePtrIterator::new(NodePtr::new::<()>(ptr::null_mut()))
+        };
+
+        Chances {
+            children,
+            parent: self.node,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Follows `action`, which must be one of this node's actions.
+    pub fn action_child(&self, action: &ActionRef<'a, G::Action, P>) -> Self {
+        assert_eq!(
+            action.parent.0, self.node.0,
+            "action is not a child of this node"
+        );
+
+        self.child(action.ptr(), Event::Action(action.action()))
+    }
+
+    /// Follows `chance`, which must be one of this node's chance nodes.
+    pub fn chance_child(&self, chance: &ChanceRef<'a, G::Chance>) -> Self {
+        assert_eq!(
+            chance.parent.0, self.node.0,
+            "chance node is not a child of this node"
+        );
+
+        self.child(chance.ptr(), Event::Chance(chance.result()))
+    }
+
+    /// Follows the chance event `chance`, if it's in the tree.  Every event of a private chance
+    /// stage leads to the node's one child.
+    pub fn chance_event_child(&self, chance: G::Chance) -> Option<Self> {
+        let node = if self.stage().is_private_chance() {
+            self.chances().next()
+        } else {
+            self.chances().find(|c| c.result() == chance)
+        }?;
+
+        Some(self.child(node.ptr(), Event::Chance(chance)))
+    }
+
+    /// Each of this node's chance events, and the node it leads to.  At a private chance stage,
+    /// that's every event the game produces, each leading to the node's one child.
+    pub fn chance_children(&self) -> Vec<(G::Chance, Self)> {
+        if !self.stage().is_private_chance() {
+            return self
+                .chances()
+                .map(|c| (c.result(), self.chance_child(&c)))
+                .collect();
+        }
+
+        let child = match self.chances().next() {
+            Some(child) => child.ptr(),
+            None => return Vec::new(),
+        };
+
+        let mut events = Vec::new();
+        G::populate_events(&self.state, &mut events);
+
+        events
+            .into_iter()
+            .map(|e| match e {
+                Event::Chance(c) => (c, self.child(child, e)),
+                _ => panic!("event must be chance"),
+            })
+            .collect()
+    }
+
+    /// Follows `event`, if it's in the tree.  The event isn't checked against the state's events.
+    pub fn follow(&self, event: Event<G::Action, G::Chance>) -> Option<Self> {
+        match event {
+            Event::Action(action) => {
+                let node = self.actions().find(|a| a.action() == action)?;
+                Some(self.action_child(&node))
+            }
+            Event::Chance(chance) => self.chance_event_child(chance),
+        }
+    }
+
+    /// Follows each event of `history` in turn.  Events of private chance stages have to be
+    /// included, even though they all lead to the same node, since they decide the state.
+    pub fn follow_path(
+        &self,
+        history: &[Event<G::Action, G::Chance>],
+    ) -> Result<Self, PathError<G::Action, G::Chance>> {
+        let mut node = self.clone();
+        let mut events = Vec::new();
+
+        for (index, &event) in history.iter().enumerate() {
+            let error = |kind| PathError { index, event, kind };
+
+            events.clear();
+            if !node.stage().is_terminal() {
+                G::populate_events(node.state(), &mut events);
+            }
+
+            if !events.contains(&event) {
+                return Err(error(PathErrorKind::Illegal));
+            }
+
+            node = node
+                .follow(event)
+                .ok_or_else(|| error(PathErrorKind::NotExpanded))?;
+        }
+
+        Ok(node)
+    }
+
+    /// Each of this node's children, and the event leading to it.
+    pub fn children(&self) -> impl Iterator<Item = (Event<G::Action, G::Chance>, Self)> + '_ {
+        let actions = self
+            .actions()
+            .map(move |a| (Event::Action(a.action()), self.action_child(&a)));
+        let chances = self
+            .chances()
+            .map(move |c| (Event::Chance(c.result()), self.chance_child(&c)));
+
+        actions.chain(chances)
+    }
+
+    fn child(&self, node: NodePtr, event: Event<G::Action, G::Chance>) -> Self {
+        let mut state = self.state.clone();
+        G::advance_state(&mut state, event);
+
+        Self {
+            node,
+            state,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// An event of a path that couldn't be followed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathError<A, C> {
+    /// The event's position in the path.
+    pub index: usize,
+    pub event: Event<A, C>,
+    pub kind: PathErrorKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathErrorKind {
+    /// The event isn't one of the state's events.
+    Illegal,
+    /// The event is legal, but its node hasn't been allocated.
+    NotExpanded,
+}
+
+impl<A, C> fmt::Display for PathError<A, C>
+where
+    A: fmt::Debug,
+    C: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.kind {
+            PathErrorKind::Illegal => "isn't legal",
+            PathErrorKind::NotExpanded => "isn't in the tree",
+        };
+
+        write!(f, "event {} ({:?}) {}", self.index, self.event, reason)
+    }
+}
+
+impl<A, C> std::error::Error for PathError<A, C>
+where
+    A: fmt::Debug,
+    C: fmt::Debug,
+{
+}
+
+/// An action node: one of the actions available at its parent, with a parameter for each of the
+/// parent's parameter indices.
+pub struct ActionRef<'a, A, P> {
+    node: *const ActionNode<A, P>,
+    parent: NodePtr,
+    parameter_count: usize,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a, A, P> Clone for ActionRef<'a, A, P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, A, P> Copy for ActionRef<'a, A, P> {}
+
+impl<'a, A, P> ActionRef<'a, A, P>
+where
+    A: Copy,
+{
+    pub fn action(&self) -> A {
+        unsafe { (*self.node).action }
+    }
+
+    pub fn parameters(&self) -> &'a [P]
+    where
+        P: 'a,
+    {
+        if self.parameter_count == 0 {
+            return &[];
+        }
+
+        unsafe { slice::from_raw_parts((*self.node).parameters, self.parameter_count) }
+    }
+
+    pub fn parameter(&self, index: usize) -> &'a P
+    where
+        P: 'a,
+    {
+        &self.parameters()[index]
+    }
+
+    pub fn ptr(&self) -> NodePtr {
+        NodePtr::new(self.node as *mut ActionNode<A, P>)
+    }
+}
+
+/// A chance node: one of the chance events available at its parent.
+pub struct ChanceRef<'a, C> {
+    node: *const ChanceNode<C>,
+    parent: NodePtr,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a, C> Clone for ChanceRef<'a, C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, C> Copy for ChanceRef<'a, C> {}
+
+impl<'a, C> ChanceRef<'a, C>
+where
+    C: Copy,
+{
+    /// The event the node was allocated for.  At a private chance stage, it stands for all of
+    /// them.
+    pub fn result(&self) -> C {
+        unsafe { (*self.node).result }
+    }
+
+    pub fn ptr(&self) -> NodePtr {
+        NodePtr::new(self.node as *mut ChanceNode<C>)
+    }
+}
+
+pub struct Actions<'a, A, P> {
+    children: NodePtrIterator,
+    parent: NodePtr,
+    parameter_count: usize,
+    _marker: PhantomData<ActionRef<'a, A, P>>,
+}
+
+impl<'a, A, P> Iterator for Actions<'a, A, P> {
+    type Item = ActionRef<'a, A, P>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.children.next().map(|child| ActionRef {
+            node: child.0 as *const ActionNode<A, P>,
+            parent: self.parent,
+            parameter_count: self.parameter_count,
+            _marker: PhantomData,
+        })
+    }
+}
+
+pub struct Chances<'a, C> {
+    children: NodePtrIterator,
+    parent: NodePtr,
+    _marker: PhantomData<ChanceRef<'a, C>>,
+}
+
+impl<'a, C> Iterator for Chances<'a, C> {
+    type Item = ChanceRef<'a, C>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.children.next().map(|child| ChanceRef {
+            node: child.0 as *const ChanceNode<C>,
+            parent: self.parent,
+            _marker: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    use util::arena::Arena;
+
+    use crate::allocator::allocate_root;
+    use crate::dummy::*;
+    use crate::{allocate_action_nodes, Game, ParameterMapping};
+
+    #[test]
+    fn test_actions() {
+        let arena = Mutex::new(Arena::with_capacity(300));
+
+        let events = [[1; 6], [2; 6], [3; 6]];
+        let parameter_count = <X as Game>::ParameterMapping::get_parameter_count(&X);
+
+        let root = allocate_root(&arena).unwrap();
+        root.add_child(allocate_action_nodes::<X, P>(&events, parameter_count, &arena).unwrap());
+
+        let actions = Actions::<[u8; 6], P> {
+            children: root.children(),
+            parent: root,
+            parameter_count,
+            _marker: PhantomData,
+        }
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            actions.iter().map(|a| a.action()).collect::<Vec<_>>(),
+            events
+        );
+
+        for (i, action) in actions.iter().enumerate() {
+            let first = i as P * 4 + 1;
+            assert_eq!(
+                action.parameters(),
+                [first, first + 1, first + 2, first + 3]
+            );
+            assert_eq!(*action.parameter(2), first + 2);
+            assert_eq!(action.parent.0, root.0);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt;
+
+use crate::game::{Event, Game};
+
+/// Writes `history` as a history string: each event's `Game::format_event` token, separated by
+/// spaces, with a '/' wherever a chance event follows an action, so that each round's actions are
+/// set apart.  Leduc Hold'em's "k b c / K b c" is a check, a bet and a call, the board card, and
+/// another bet and call.
+pub fn format_history<G>(history: &[Event<G::Action, G::Chance>]) -> String
+where
+    G: Game,
+{
+    let mut string = String::new();
+
+    for (i, &event) in history.iter().enumerate() {
+        if i > 0 {
+            let new_round = matches!(
+                (history[i - 1], event),
+                (Event::Action(_), Event::Chance(_))
+            );
+            string.push_str(if new_round { " / " } else { " " });
+        }
+
+        string.push_str(&G::format_event(event));
+    }
+
+    string
+}
+
+/// Parses a history string, following it from `root_state` to check that each event is one of the
+/// events of the state it's reached.  Tokens can be separated by any whitespace, and '/' is only
+/// a separator, so it can be left out or put anywhere between tokens.
+#[allow(clippy::type_complexity)]
+pub fn parse_history<G>(
+    root_state: &G::State,
+    history: &str,
+) -> Result<Vec<Event<G::Action, G::Chance>>, HistoryError>
+where
+    G: Game,
+{
+    let mut state = root_state.clone();
+
+    history
+        .split(|c: char| c == '/' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+        .enumerate()
+        .map(|(index, token)| {
+            let event = G::parse_event(&state, token).ok_or_else(|| HistoryError {
+                index,
+                token: token.to_string(),
+            })?;
+
+            G::advance_state(&mut state, event);
+            Ok(event)
+        })
+        .collect()
+}
+
+/// A token of a history string that isn't an event of the state it's reached.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryError {
+    /// The token's position in the history, not counting separators.
+    pub index: usize,
+    pub token: String,
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "token {} ({:?}) isn't a legal event",
+            self.index, self.token
+        )
+    }
+}
+
+impl std::error::Error for HistoryError {}
@@ -1,14 +1,25 @@
 mod allocator;
+mod conformance;
 mod estimator;
 mod game;
+mod handle;
+mod history;
 mod node;
+mod persist;
 
 #[cfg(test)]
 mod dummy;
 
 pub use self::allocator::{
-    allocate_action_nodes, allocate_chance_nodes, allocate_root_node, allocate_tree,
+    allocate_action_nodes, allocate_chance_nodes, allocate_root_node, allocate_tree, expand_node,
+    find_root_node, populate_tree_events,
 };
-pub use self::estimator::TreeEstimator;
-pub use self::game::{Event, Game, Parameter, ParameterMapping, Stage};
+pub use self::conformance::{check_game, sample_game, Violation, ViolationKind};
+pub use self::estimator::{ByteCounts, NodeCounts, TreeEstimator, TreeStatistics};
+pub use self::game::{Event, Game, Parameter, ParameterMapping, Stage, SubtreeKey};
+pub use self::handle::{
+    ActionRef, Actions, ChanceRef, Chances, NodeHandle, PathError, PathErrorKind,
+};
+pub use self::history::{format_history, parse_history, HistoryError};
 pub use self::node::{ActionNode, ChanceNode, NodePtr, NodePtrIterator, RootNode};
+pub use self::persist::{load_tree, save_tree, LoadError, PersistentGame, PersistentParameter};
@@ -1,3 +1,6 @@
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
 use util::volatile::Volatile;
 
 /// This will point to a node type.  The game state will know which.
@@ -10,19 +13,40 @@ impl NodePtr {
         Self(ptr as *mut ())
     }
 
-    /// Caller must ensure that the iterator returned does not outlive this node.
+    /// Caller must ensure that the iterator returned does not outlive this node.  A null node, like
+    /// the ones measuring allocators return, has no children.
     pub fn children(&self) -> NodePtrIterator {
-        let node = self.0 as *mut NodeRelationships;
-        NodePtrIterator::new(unsafe { (*node).first_child.read() })
+        if self.0.is_null() {
+            return NodePtrIterator::new(*self);
+        }
+
+        NodePtrIterator::new(NodePtr(self.first_child().load(Ordering::Acquire)))
+    }
+
+    /// Makes `first_child` this node's first child if it has no children yet, and returns the
+    /// node's first child either way.  This is safe to call while other threads are reading the
+    /// node: the release ordering makes sure that everything written to the children beforehand
+    /// is visible to any thread that finds them through `children`.
+    pub fn publish_children(&self, first_child: NodePtr) -> NodePtr {
+        match self.first_child().compare_exchange(
+            ptr::null_mut(),
+            first_child.0,
+            Ordering::Release,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => first_child,
+            Err(existing) => NodePtr(existing),
+        }
     }
 
     pub fn next_sibling(&self) -> Option<NodePtr> {
         let node = self.0 as *mut NodeRelationships;
         let next_sibling = unsafe { (*node).next_sibling };
-        (!next_sibling.0.is_null()).then(|| next_sibling)
+        (!next_sibling.0.is_null()).then_some(next_sibling)
     }
 
-    /// Caller must ensure that `child` is the same type as any other children.
+    /// Caller must ensure that `child` is the same type as any other children, and that no other
+    /// thread is using this node.
     pub fn add_child(&self, child: NodePtr) {
         let node = self.0 as *mut NodeRelationships;
         let sibling = unsafe { (*node).first_child.read() };
@@ -37,6 +61,15 @@ impl NodePtr {
     }
 }
 
+impl NodePtr {
+    fn first_child(&self) -> &AtomicPtr<()> {
+        let node = self.0 as *mut NodeRelationships;
+        // A `Volatile<NodePtr>` is a transparent wrapper around a `*mut ()`, which has the same
+        // layout as an `AtomicPtr<()>`.
+        unsafe { &*(&(*node).first_child as *const Volatile<NodePtr> as *const AtomicPtr<()>) }
+    }
+}
+
 /// Must match the layout of the beginning of all node types.
 #[repr(C)]
 pub struct NodeRelationships {
@@ -0,0 +1,300 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::slice;
+use util::arena::{self, SharedAllocator};
+
+use crate::allocator::{allocate_root, expand_node, populate_tree_events};
+use crate::game::{Game, Parameter, ParameterMapping, Stage};
+use crate::handle::NodeHandle;
+use crate::node::{ActionNode, NodePtr};
+
+const MAGIC: &[u8; 8] = b"PSOLVTRE";
+const VERSION: u32 = 2;
+const BYTE_ORDER: u32 = 0x01020304;
+
+/// A parameter that can be saved as its raw bytes.
+///
+/// # Safety
+///
+/// The type must not contain pointers or anything else that is only meaningful in the process
+/// that wrote it, and any bytes copied out of a valid value must make a valid value when copied
+/// back in.
+pub unsafe trait PersistentParameter: Parameter {
+    /// Identifies the parameter in saved trees.  Parameters with different layouts, or different
+    /// meanings for the same layout, must have different IDs.
+    const ID: &'static str;
+}
+
+/// A game whose trees can be saved.
+pub trait PersistentGame: Game {
+    /// Identifies the game in saved trees.  Games with different trees, like different numbers of
+    /// players of the same game, must have different IDs.
+    const ID: &'static str;
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Arena(arena::Error),
+    /// The data isn't a tree, or is a tree of a different kind.
+    Format(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(error) => write!(f, "{}", error),
+            LoadError::Arena(error) => write!(f, "{}", error),
+            LoadError::Format(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(error: io::Error) -> Self {
+        LoadError::Io(error)
+    }
+}
+
+impl From<arena::Error> for LoadError {
+    fn from(error: arena::Error) -> Self {
+        LoadError::Arena(error)
+    }
+}
+
+/// Writes the tree below `root` and its parameters to `writer`, along with `iteration`, which
+/// is handed back by `load_tree`.  `root` should be the tree's root, since the tree is loaded
+/// below the state it was saved from.
+///
+/// Nodes are written in the order `Game::populate_events` produces them, so the format holds no
+/// pointers, and only records which nodes have children.  Parameters are written as raw bytes in
+/// the machine's byte order; trees can't be loaded on a machine with a different byte order.
+pub fn save_tree<G, P, W>(
+    root: &NodeHandle<'_, G, P>,
+    iteration: u64,
+    mut writer: W,
+) -> io::Result<()>
+where
+    G: PersistentGame,
+    P: PersistentParameter,
+    W: Write,
+{
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_ne_bytes())?;
+    writer.write_all(&BYTE_ORDER.to_ne_bytes())?;
+    write_string(&mut writer, G::ID)?;
+    write_string(&mut writer, P::ID)?;
+    writer.write_all(&(mem::size_of::<P>() as u32).to_ne_bytes())?;
+    writer.write_all(&iteration.to_ne_bytes())?;
+
+    fn save_children<G, P, W>(node: NodePtr, state: &G::State, writer: &mut W) -> io::Result<()>
+    where
+        G: Game,
+        P: PersistentParameter,
+        W: Write,
+    {
+        let stage = G::get_stage(state);
+
+        if stage.is_terminal() {
+            return Ok(());
+        }
+
+        let child_count = node.children().count();
+
+        writer.write_all(&(child_count as u32).to_ne_bytes())?;
+
+        if child_count == 0 {
+            return Ok(());
+        }
+
+        if stage.is_action() {
+            let parameter_count = G::ParameterMapping::get_parameter_count(state);
+
+            for child in node.children() {
+                let parameters =
+                    unsafe { (*(child.0 as *mut ActionNode<G::Action, P>)).parameters };
+                let bytes = unsafe {
+                    slice::from_raw_parts(
+                        parameters as *const u8,
+                        parameter_count * mem::size_of::<P>(),
+                    )
+                };
+
+                writer.write_all(bytes)?;
+            }
+        }
+
+        let mut events = Vec::new();
+        populate_tree_events::<G>(state, &mut events);
+
+        for (&event, child) in events.iter().zip(node.children()) {
+            let mut next_state = state.clone();
+            G::advance_state(&mut next_state, event);
+
+            save_children::<G, P, W>(child, &next_state, writer)?;
+        }
+
+        Ok(())
+    }
+
+    save_children::<G, P, W>(root.ptr(), root.state(), &mut writer)
+}
+
+/// Reads a tree written by `save_tree` into `arena`, and returns its root and the iteration it
+/// was saved with.  `root_state` must be the state the tree was saved from.  The root borrows
+/// `arena`, so the tree can't outlive it.
+pub fn load_tree<'a, G, P, A, R>(
+    root_state: &G::State,
+    arena: &'a A,
+    mut reader: R,
+) -> Result<(NodeHandle<'a, G, P>, u64), LoadError>
+where
+    G: PersistentGame,
+    P: PersistentParameter,
+    A: SharedAllocator,
+    R: Read,
+{
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(LoadError::Format("not a saved tree".to_string()));
+    }
+
+    let version = read_u32(&mut reader)?;
+    if version != VERSION {
+        return Err(LoadError::Format(format!(
+            "unsupported version {}",
+            version
+        )));
+    }
+
+    if read_u32(&mut reader)? != BYTE_ORDER {
+        return Err(LoadError::Format(
+            "saved with a different byte order".to_string(),
+        ));
+    }
+
+    let game = read_string(&mut reader)?;
+    if game != G::ID {
+        return Err(LoadError::Format(format!("saved for game {}", game)));
+    }
+
+    let parameter = read_string(&mut reader)?;
+    if parameter != P::ID {
+        return Err(LoadError::Format(format!(
+            "saved with parameter {}",
+            parameter
+        )));
+    }
+
+    let parameter_size = read_u32(&mut reader)? as usize;
+    if parameter_size != mem::size_of::<P>() {
+        return Err(LoadError::Format(format!(
+            "saved with parameters of {} bytes",
+            parameter_size
+        )));
+    }
+
+    let mut iteration = [0; 8];
+    reader.read_exact(&mut iteration)?;
+    let iteration = u64::from_ne_bytes(iteration);
+
+    fn load_children<G, P, A, R>(
+        node: NodePtr,
+        state: &G::State,
+        arena: &A,
+        reader: &mut R,
+    ) -> Result<(), LoadError>
+    where
+        G: Game,
+        P: PersistentParameter,
+        A: SharedAllocator,
+        R: Read,
+    {
+        let stage = G::get_stage(state);
+
+        if stage.is_terminal() {
+            return Ok(());
+        }
+
+        let child_count = read_u32(reader)? as usize;
+
+        if child_count == 0 {
+            return Ok(());
+        }
+
+        let mut events = Vec::new();
+        populate_tree_events::<G>(state, &mut events);
+
+        if child_count != events.len() {
+            return Err(LoadError::Format(format!(
+                "saved node has {} children, but the game has {} events",
+                child_count,
+                events.len()
+            )));
+        }
+
+        expand_node::<G, P>(node, state, arena)?;
+
+        if stage.is_action() {
+            let parameter_count = G::ParameterMapping::get_parameter_count(state);
+
+            for child in node.children() {
+                let parameters =
+                    unsafe { (*(child.0 as *mut ActionNode<G::Action, P>)).parameters };
+                let bytes = unsafe {
+                    slice::from_raw_parts_mut(
+                        parameters as *mut u8,
+                        parameter_count * mem::size_of::<P>(),
+                    )
+                };
+
+                reader.read_exact(bytes)?;
+            }
+        }
+
+        for (&event, child) in events.iter().zip(node.children()) {
+            let mut next_state = state.clone();
+            G::advance_state(&mut next_state, event);
+
+            load_children::<G, P, A, R>(child, &next_state, arena, reader)?;
+        }
+
+        Ok(())
+    }
+
+    let root = allocate_root(arena)?;
+    load_children::<G, P, A, R>(root, root_state, arena, &mut reader)?;
+
+    // The tree was built from the game's events below `root_state`.
+    Ok((
+        unsafe { NodeHandle::new(root, root_state.clone()) },
+        iteration,
+    ))
+}
+
+fn write_string<W: Write>(writer: &mut W, string: &str) -> io::Result<()> {
+    writer.write_all(&(string.len() as u32).to_ne_bytes())?;
+    writer.write_all(string.as_bytes())
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_ne_bytes(bytes))
+}
+
+fn read_string<R: Read>(reader: &mut R) -> Result<String, LoadError> {
+    let len = read_u32(reader)? as usize;
+
+    let mut bytes = Vec::new();
+    reader.by_ref().take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    String::from_utf8(bytes).map_err(|_| LoadError::Format("invalid ID".to_string()))
+}
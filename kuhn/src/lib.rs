@@ -1,9 +1,10 @@
 use rand::{self, Rng};
 
-use game_tree::{Event, Game, ParameterMapping, Stage};
+use game_tree::{Event, Game, ParameterMapping, PersistentGame, Stage};
 
 #[derive(Clone, Copy, Debug)]
 pub enum KuhnStage {
+    Deal,
     PlayerAction(u8),
     Showdown,
 }
@@ -12,25 +13,29 @@ impl Stage for KuhnStage {
     fn is_action(&self) -> bool {
         match self {
             KuhnStage::PlayerAction(_) => true,
-            KuhnStage::Showdown => false,
+            KuhnStage::Deal | KuhnStage::Showdown => false,
         }
     }
 
     fn is_chance(&self) -> bool {
-        false
+        matches!(self, KuhnStage::Deal)
     }
 
     fn is_terminal(&self) -> bool {
         match self {
-            KuhnStage::PlayerAction(_) => false,
+            KuhnStage::Deal | KuhnStage::PlayerAction(_) => false,
             KuhnStage::Showdown => true,
         }
     }
 
+    fn is_private_chance(&self) -> bool {
+        matches!(self, KuhnStage::Deal)
+    }
+
     fn player_to_act(&self) -> Option<usize> {
         match self {
             KuhnStage::PlayerAction(p) => Some(*p as usize),
-            KuhnStage::Showdown => None,
+            KuhnStage::Deal | KuhnStage::Showdown => None,
         }
     }
 }
@@ -59,80 +64,174 @@ impl<const N: usize> KuhnState<N> {
         }
     }
 
+    /// A state whose cards haven't been dealt yet, so the root of its tree is a chance node that
+    /// deals them.  A full-width solver can iterate from it without sampling deals itself.
+    pub fn undealt() -> Self {
+        Self {
+            stage: KuhnStage::Deal,
+            ..Self::from_cards([0; N])
+        }
+    }
+
     pub fn random<R: Rng>(rng: &mut R) -> Self {
-        let mut cards = [0u8; N];
+        Self::from_cards(random_cards(rng))
+    }
+
+    /// Each player's card, from 0, the lowest, to N, the highest.
+    pub fn cards(&self) -> [u8; N] {
+        self.cards
+    }
+}
+
+fn random_cards<R: Rng, const N: usize>(rng: &mut R) -> [u8; N] {
+    let mut cards = [0u8; N];
+
+    for (c, d) in cards
+        .iter_mut()
+        .zip(rand::seq::index::sample(rng, N + 1, N))
+    {
+        *c = d as u8;
+    }
+
+    cards
+}
+
+/// Every ordered way of dealing N of the N + 1 cards, in the order the deal's events are in.
+pub fn deals<const N: usize>() -> Vec<[u8; N]> {
+    fn deal<const N: usize>(cards: &mut [u8; N], i: usize, deals: &mut Vec<[u8; N]>) {
+        if i == N {
+            deals.push(*cards);
+            return;
+        }
 
-        for (c, d) in cards
-            .iter_mut()
-            .zip(rand::seq::index::sample(rng, N + 1, N))
-        {
-            *c = d as u8;
+        for card in 0..=N as u8 {
+            if !cards[..i].contains(&card) {
+                cards[i] = card;
+                deal(cards, i + 1, deals);
+            }
         }
+    }
+
+    let mut deals = Vec::new();
+    deal(&mut [0; N], 0, &mut deals);
+    deals
+}
+
+/// The deal at `index` in `deals`, without listing the deals before it.
+fn nth_deal<const N: usize>(mut index: usize) -> [u8; N] {
+    let mut unused = (0..=N as u8).collect::<Vec<_>>();
+    let mut cards = [0; N];
 
-        Self::from_cards(cards)
+    // Each choice of the i-th card is followed by (N - i)! ways of dealing the rest.
+    for (i, card) in cards.iter_mut().enumerate() {
+        let rest = (1..=N - i).product::<usize>();
+        *card = unused.remove(index / rest);
+        index %= rest;
     }
+
+    cards
+}
+
+/// The number of ordered ways of dealing N of the N + 1 cards, (N + 1)!.
+fn deal_count<const N: usize>() -> usize {
+    (1..=N + 1).product()
+}
+
+/// The most players Kuhn poker's cards can be named for, and its trees saved with.
+pub const MAX_PLAYERS: usize = 12;
+
+/// Each number of players' `PersistentGame::ID`, up to `MAX_PLAYERS`.
+const PERSISTENT_IDS: [&str; MAX_PLAYERS + 1] = [
+    "kuhn-0", "kuhn-1", "kuhn-2", "kuhn-3", "kuhn-4", "kuhn-5", "kuhn-6", "kuhn-7", "kuhn-8",
+    "kuhn-9", "kuhn-10", "kuhn-11", "kuhn-12",
+];
+
+/// The name of a card, counting down from the king: with 2 players, the cards are J, Q and K.
+fn card_name<const N: usize>(card: u8) -> char {
+    assert!(N <= MAX_PLAYERS, "too many players to describe");
+
+    const CARDS: [char; MAX_PLAYERS + 1] = [
+        'A', '2', '3', '4', '5', '6', '7', '8', '9', 'T', 'J', 'Q', 'K',
+    ];
+
+    CARDS[MAX_PLAYERS - N + card as usize]
 }
 
 pub struct KuhnGame<const N: usize>;
 
 impl<const N: usize> Game for KuhnGame<N> {
     type Action = KuhnAction;
-    type Chance = ();
+    type Chance = [u8; N];
     type ParameterMapping = KuhnParameterMapping<N>;
     type Stage = KuhnStage;
     type State = KuhnState<N>;
 
     fn advance_state(state: &mut Self::State, event: Event<Self::Action, Self::Chance>) {
-        if let Event::Action(action) = event {
-            if let KuhnStage::PlayerAction(player) = state.stage {
-                let next_player = (player + 1) as usize % N;
-
-                if state.bet {
-                    if matches!(action, KuhnAction::Bet) {
-                        // This is a call.
-                        state.called[player as usize] = true;
-                    }
-
-                    if state.called[next_player] {
-                        state.stage = KuhnStage::Showdown;
-                    } else {
-                        state.stage = KuhnStage::PlayerAction(next_player as u8);
-                    }
-                } else if matches!(action, KuhnAction::Bet) {
-                    // This is a bet.
-                    state.bet = true;
+        let action = match event {
+            Event::Action(action) => action,
+            Event::Chance(cards) => {
+                assert!(
+                    matches!(state.stage, KuhnStage::Deal),
+                    "cards have already been dealt"
+                );
+
+                state.cards = cards;
+                state.stage = KuhnStage::PlayerAction(0);
+                return;
+            }
+        };
+
+        if let KuhnStage::PlayerAction(player) = state.stage {
+            let next_player = (player + 1) as usize % N;
+
+            if state.bet {
+                if matches!(action, KuhnAction::Bet) {
+                    // This is a call.
                     state.called[player as usize] = true;
-                    state.stage = KuhnStage::PlayerAction(next_player as u8);
-                } else if next_player < player as usize {
-                    // This is a check, as the last player to act.
+                }
+
+                if state.called[next_player] {
                     state.stage = KuhnStage::Showdown;
                 } else {
-                    // This is a check, with more players to act after.
                     state.stage = KuhnStage::PlayerAction(next_player as u8);
                 }
+            } else if matches!(action, KuhnAction::Bet) {
+                // This is a bet.
+                state.bet = true;
+                state.called[player as usize] = true;
+                state.stage = KuhnStage::PlayerAction(next_player as u8);
+            } else if next_player < player as usize {
+                // This is a check, as the last player to act.
+                state.stage = KuhnStage::Showdown;
             } else {
-                panic!("cannot advance a state that is at showdown");
+                // This is a check, with more players to act after.
+                state.stage = KuhnStage::PlayerAction(next_player as u8);
             }
         } else {
-            panic!("there are no chance events in kuhn poker");
+            panic!("cannot apply an action outside of a player action stage");
         }
     }
 
     fn populate_events(state: &Self::State, events: &mut Vec<Event<Self::Action, Self::Chance>>) {
         events.clear();
 
-        if !matches!(state.stage, KuhnStage::Showdown) {
-            events.push(Event::Action(KuhnAction::Bet));
-            events.push(Event::Action(KuhnAction::Check));
+        match state.stage {
+            KuhnStage::Deal => events.extend(deals::<N>().into_iter().map(Event::Chance)),
+            KuhnStage::PlayerAction(_) => {
+                events.push(Event::Action(KuhnAction::Bet));
+                events.push(Event::Action(KuhnAction::Check));
+            }
+            KuhnStage::Showdown => (),
         }
     }
 
     fn get_chance_weight(_state: &Self::State, _event: Self::Chance) -> f32 {
-        panic!("there are no chance events in kuhn poker")
+        1.0 / deal_count::<N>() as f32
     }
 
-    fn sample_chance<R: Rng>(_state: &Self::State, _rng: &mut R) -> (Self::Chance, usize) {
-        panic!("there are no chance events in kuhn poker")
+    fn sample_chance<R: Rng>(_state: &Self::State, rng: &mut R) -> (Self::Chance, usize) {
+        let index = rng.gen_range(0..deal_count::<N>());
+        (nth_deal::<N>(index), index)
     }
 
     fn get_stage(state: &Self::State) -> Self::Stage {
@@ -141,6 +240,7 @@ impl<const N: usize> Game for KuhnGame<N> {
 
     fn get_branching_hint(state: &Self::State) -> usize {
         match state.stage {
+            KuhnStage::Deal => deal_count::<N>(),
             KuhnStage::PlayerAction(_) => 2,
             KuhnStage::Showdown => 0,
         }
@@ -186,6 +286,24 @@ impl<const N: usize> Game for KuhnGame<N> {
             }
         });
     }
+
+    /// Actions are "b" for a bet or call and "k" for a check or fold, as in the other games, and
+    /// deals are each player's card, like "KJ".
+    fn format_event(event: Event<Self::Action, Self::Chance>) -> String {
+        match event {
+            Event::Action(KuhnAction::Bet) => "b".to_string(),
+            Event::Action(KuhnAction::Check) => "k".to_string(),
+            Event::Chance(cards) => cards.iter().map(|&c| card_name::<N>(c)).collect(),
+        }
+    }
+}
+
+impl<const N: usize> PersistentGame for KuhnGame<N> {
+    // Each number of players has its own tree.
+    const ID: &'static str = {
+        assert!(N <= MAX_PLAYERS, "Kuhn poker has at most 12 players");
+        PERSISTENT_IDS[N]
+    };
 }
 
 pub struct KuhnParameterMapping<const N: usize>;
@@ -206,8 +324,6 @@ impl<const N: usize> ParameterMapping for KuhnParameterMapping<N> {
     }
 
     fn get_parameter_description(state: &Self::State, alternate_index: Option<usize>) -> String {
-        assert!(N < 13, "too many players to describe");
-
         let index = if let KuhnStage::PlayerAction(player) = state.stage {
             if let Some(index) = alternate_index {
                 index
@@ -220,12 +336,7 @@ impl<const N: usize> ParameterMapping for KuhnParameterMapping<N> {
 
         assert!(index < N + 1, "parameter index is out of bounds");
 
-        const CARDS: [char; 13] = [
-            'A', '2', '3', '4', '5', '6', '7', '8', '9', 'T', 'J', 'Q', 'K',
-        ];
-
-        let card_index = 12 - N + index;
-        CARDS[card_index].to_string()
+        card_name::<N>(index as u8).to_string()
     }
 }
 
@@ -233,13 +344,9 @@ impl<const N: usize> ParameterMapping for KuhnParameterMapping<N> {
 mod tests {
     use super::*;
 
-    use std::sync::Mutex;
-
     use rand::SeedableRng;
 
-    use game_tree::{allocate_tree, TreeEstimator};
-    use solver::{dump_strategy, Cfr, CfrParameter, Solver};
-    use util::arena::Arena;
+    use game_tree::{format_history, parse_history};
     use util::rng::JKiss32Rng;
 
     #[test]
@@ -348,57 +455,52 @@ mod tests {
     }
 
     #[test]
-    fn test_tree_estimate() {
-        const N: usize = 3;
-        let root_state = KuhnState::from_cards([0; N]);
-
-        let estimator = TreeEstimator::<KuhnGame<N>, CfrParameter>::from_root(root_state);
-
-        assert_eq!(estimator.action_nodes(), 24);
-        assert_eq!(estimator.chance_nodes(), 0);
-        assert_eq!(estimator.parameters(), 96);
-        assert_eq!(estimator.memory_bounds(), (1552, 1559));
-    }
-
-    #[test]
-    fn test_tree_allocation_size() {
-        const N: usize = 2;
-        let root_state = KuhnState::from_cards([0; N]);
-
-        let estimator = TreeEstimator::<KuhnGame<N>, CfrParameter>::from_root(root_state);
-        let memory_bounds = estimator.memory_bounds();
-
-        let arena = Mutex::new(Arena::with_capacity(10000));
-        let _root = allocate_tree::<KuhnGame<N>, CfrParameter>(&root_state, &arena)
-            .expect("could not allocate tree");
-        let size = arena.lock().unwrap().len();
+    fn test_sample_deal() {
+        let deals = deals::<3>();
+        for (i, deal) in deals.iter().enumerate() {
+            assert_eq!(nth_deal::<3>(i), *deal);
+        }
 
-        assert!(size >= memory_bounds.0, "tree is smaller than expected");
-        assert!(size <= memory_bounds.1, "tree is larger than expected");
+        let mut rng = JKiss32Rng::seed_from_u64(7);
+        let state = KuhnState::<3>::undealt();
+        for _ in 0..100 {
+            let (deal, index) = KuhnGame::<3>::sample_chance(&state, &mut rng);
+            assert_eq!(deals[index], deal);
+        }
     }
 
     #[test]
-    fn test_kuhn_solve() {
-        const N: usize = 3;
-        let root_state = KuhnState::from_cards([0, 1, 2]);
-
-        let arena = {
-            let estimator = TreeEstimator::<KuhnGame<N>, CfrParameter>::from_root(root_state);
-            Mutex::new(Arena::with_capacity(estimator.memory_bounds().1))
-        };
-
-        let root = allocate_tree::<KuhnGame<N>, CfrParameter>(&root_state, &arena)
-            .expect("could not allocate tree");
+    fn test_history() {
+        let history = [
+            Event::Chance([2, 0, 1]),
+            Event::Action(KuhnAction::Check),
+            Event::Action(KuhnAction::Bet),
+        ];
 
-        let mut solver = Cfr::<N>;
+        assert_eq!(format_history::<KuhnGame<3>>(&history), "QTJ k b");
+        assert_eq!(
+            parse_history::<KuhnGame<3>>(&KuhnState::undealt(), "QTJ k b"),
+            Ok(history.to_vec())
+        );
 
-        let mut rng = JKiss32Rng::seed_from_u64(0);
+        // A card can't be dealt twice.
+        assert_eq!(
+            parse_history::<KuhnGame<3>>(&KuhnState::undealt(), "QQJ k b")
+                .unwrap_err()
+                .index,
+            0
+        );
 
-        for i in 0..100000 {
-            let state = KuhnState::random(&mut rng);
-            <Cfr<N> as Solver<KuhnGame<N>>>::iterate(&mut solver, root, state, i);
-        }
+        let history = [Event::Chance([2, 0]), Event::Action(KuhnAction::Check)];
+        assert_eq!(format_history::<KuhnGame<2>>(&history), "KJ k");
+        assert_eq!(
+            parse_history::<KuhnGame<2>>(&KuhnState::undealt(), "KJ k"),
+            Ok(history.to_vec())
+        );
 
-        dump_strategy::<KuhnGame<N>, Cfr<N>, CfrParameter>(root, root_state, &solver);
+        // Nobody acts after a bet is called.
+        let error =
+            parse_history::<KuhnGame<2>>(&KuhnState::from_cards([0, 1]), "b b k").unwrap_err();
+        assert_eq!((error.index, error.token.as_str()), (2, "k"));
     }
 }
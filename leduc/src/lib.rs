@@ -0,0 +1,601 @@
+use rand::{self, Rng};
+
+use game_tree::{Event, Game, ParameterMapping, PersistentGame, Stage};
+
+/// Each player's ante.
+const ANTE: u32 = 1;
+
+/// The fixed size of bets and raises in each round.
+const BET_SIZES: [u32; 2] = [2, 4];
+
+/// The number of bets and raises allowed in each round.
+const RAISE_CAP: u8 = 2;
+
+/// Cards are ranks, from 0 for a jack to 2 for a king.  There are two of each.
+const RANKS: u8 = 3;
+
+const CARDS: [char; RANKS as usize] = ['J', 'Q', 'K'];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LeducStage {
+    /// The players' cards are dealt.
+    PrivateDeal,
+    PlayerAction(u8),
+    /// The public card is dealt.
+    Deal,
+    Terminal,
+}
+
+impl Stage for LeducStage {
+    fn is_action(&self) -> bool {
+        matches!(self, LeducStage::PlayerAction(_))
+    }
+
+    fn is_chance(&self) -> bool {
+        matches!(self, LeducStage::PrivateDeal | LeducStage::Deal)
+    }
+
+    fn is_terminal(&self) -> bool {
+        matches!(self, LeducStage::Terminal)
+    }
+
+    fn is_private_chance(&self) -> bool {
+        matches!(self, LeducStage::PrivateDeal)
+    }
+
+    fn player_to_act(&self) -> Option<usize> {
+        match self {
+            LeducStage::PlayerAction(p) => Some(*p as usize),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LeducAction {
+    Fold,
+    Check,
+    Call,
+    Bet,
+    Raise,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LeducChance {
+    /// Each player's rank.
+    Hands([u8; 2]),
+    /// The public card's rank.
+    Board(u8),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LeducState {
+    cards: [u8; 2],
+    board: Option<u8>,
+    round: u8,
+    committed: [u32; 2],
+    /// The number of bets and raises made this round.
+    raises: u8,
+    /// Whether the first player checked to open this round.
+    checked: bool,
+    folded: Option<u8>,
+    stage: LeducStage,
+}
+
+impl LeducState {
+    /// `cards` are the players' ranks, from 0 for a jack to 2 for a king.
+    pub fn from_cards(cards: [u8; 2]) -> Self {
+        assert!(
+            cards.iter().all(|&c| c < RANKS),
+            "cards must be between 0 and 2"
+        );
+
+        Self {
+            cards,
+            board: None,
+            round: 0,
+            committed: [ANTE; 2],
+            raises: 0,
+            checked: false,
+            folded: None,
+            stage: LeducStage::PlayerAction(0),
+        }
+    }
+
+    /// A state whose cards haven't been dealt yet, so the root of its tree is a chance node that
+    /// deals them.  A full-width solver can iterate from it without sampling deals itself.
+    pub fn undealt() -> Self {
+        Self {
+            stage: LeducStage::PrivateDeal,
+            ..Self::from_cards([0; 2])
+        }
+    }
+
+    pub fn random<R: Rng>(rng: &mut R) -> Self {
+        Self::from_cards(random_cards(rng))
+    }
+
+    /// Every ordered way of dealing two of the six cards, as a state with its probability.  Cards
+    /// of the same rank make the same state, so states repeat, and each is as likely as the others.
+    pub fn deals() -> Vec<(Self, f32)> {
+        let cards = 2 * RANKS;
+        let probability = 1.0 / (cards as usize * (cards as usize - 1)) as f32;
+
+        let mut deals = Vec::new();
+        for a in 0..cards {
+            for b in (0..cards).filter(|&b| b != a) {
+                deals.push((Self::from_cards([a / 2, b / 2]), probability));
+            }
+        }
+        deals
+    }
+
+    /// Each player's rank, from 0 for a jack to 2 for a king.
+    pub fn cards(&self) -> [u8; 2] {
+        self.cards
+    }
+
+    pub fn board(&self) -> Option<u8> {
+        self.board
+    }
+
+    pub fn committed(&self) -> [u32; 2] {
+        self.committed
+    }
+
+    /// The number of cards of `rank` that could still be the board card.
+    fn remaining(&self, rank: u8) -> usize {
+        2 - self.cards.iter().filter(|&&c| c == rank).count()
+    }
+
+    fn end_round(&mut self) {
+        if self.round == 0 {
+            self.stage = LeducStage::Deal;
+        } else {
+            self.stage = LeducStage::Terminal;
+        }
+    }
+}
+
+fn random_cards<R: Rng>(rng: &mut R) -> [u8; 2] {
+    let mut cards = [0u8; 2];
+
+    for (c, d) in cards
+        .iter_mut()
+        .zip(rand::seq::index::sample(rng, 2 * RANKS as usize, 2))
+    {
+        *c = (d / 2) as u8;
+    }
+
+    cards
+}
+
+/// Two-player Leduc Hold'em.  Each player antes 1 and is dealt one of six cards, two each of
+/// jack, queen and king.  A betting round with bets of 2 follows, then a public card is dealt by a
+/// chance node, and a second betting round with bets of 4.  Each round allows a bet and a raise.
+/// A player who pairs the public card wins at showdown, and otherwise the higher card wins.
+///
+/// The private cards are either part of the root state, or dealt at the root of an undealt state
+/// by a private chance node, whose events are the nine pairs of ranks.  The public card's chance
+/// events are its three ranks, weighted by how many of each are left, so every deal shares the same
+/// tree.
+pub struct LeducGame;
+
+impl Game for LeducGame {
+    type Action = LeducAction;
+    type Chance = LeducChance;
+    type ParameterMapping = LeducParameterMapping;
+    type Stage = LeducStage;
+    type State = LeducState;
+
+    fn advance_state(state: &mut Self::State, event: Event<Self::Action, Self::Chance>) {
+        match event {
+            Event::Action(action) => {
+                let player = if let LeducStage::PlayerAction(player) = state.stage {
+                    player as usize
+                } else {
+                    panic!("cannot apply an action outside of a player action stage");
+                };
+                let other = 1 - player;
+
+                match action {
+                    LeducAction::Fold => {
+                        state.folded = Some(player as u8);
+                        state.stage = LeducStage::Terminal;
+                    }
+                    LeducAction::Check => {
+                        if state.checked {
+                            state.end_round();
+                        } else {
+                            state.checked = true;
+                            state.stage = LeducStage::PlayerAction(other as u8);
+                        }
+                    }
+                    LeducAction::Call => {
+                        state.committed[player] = state.committed[other];
+                        state.end_round();
+                    }
+                    LeducAction::Bet | LeducAction::Raise => {
+                        assert!(state.raises < RAISE_CAP, "the raise cap has been reached");
+
+                        state.committed[player] =
+                            state.committed[other] + BET_SIZES[state.round as usize];
+                        state.raises += 1;
+                        state.stage = LeducStage::PlayerAction(other as u8);
+                    }
+                }
+            }
+            Event::Chance(LeducChance::Hands(cards)) => {
+                assert!(
+                    matches!(state.stage, LeducStage::PrivateDeal),
+                    "cards have already been dealt"
+                );
+
+                *state = LeducState::from_cards(cards);
+            }
+            Event::Chance(LeducChance::Board(rank)) => {
+                assert!(
+                    matches!(state.stage, LeducStage::Deal),
+                    "cannot deal a card outside of a deal stage"
+                );
+
+                // Ranks with none left have a chance weight of 0, but can still be dealt, so that
+                // every deal can walk the same tree.
+                state.board = Some(rank);
+                state.round = 1;
+                state.raises = 0;
+                state.checked = false;
+                state.stage = LeducStage::PlayerAction(0);
+            }
+        }
+    }
+
+    fn populate_events(state: &Self::State, events: &mut Vec<Event<Self::Action, Self::Chance>>) {
+        events.clear();
+
+        match state.stage {
+            LeducStage::PlayerAction(player) => {
+                let player = player as usize;
+
+                if state.committed[player] < state.committed[1 - player] {
+                    events.push(Event::Action(LeducAction::Fold));
+                    events.push(Event::Action(LeducAction::Call));
+                    if state.raises < RAISE_CAP {
+                        events.push(Event::Action(LeducAction::Raise));
+                    }
+                } else {
+                    events.push(Event::Action(LeducAction::Check));
+                    events.push(Event::Action(LeducAction::Bet));
+                }
+            }
+            LeducStage::PrivateDeal => events.extend(
+                (0..RANKS)
+                    .flat_map(|a| (0..RANKS).map(move |b| [a, b]))
+                    .map(|cards| Event::Chance(LeducChance::Hands(cards))),
+            ),
+            LeducStage::Deal => {
+                events.extend((0..RANKS).map(|rank| Event::Chance(LeducChance::Board(rank))))
+            }
+            LeducStage::Terminal => (),
+        }
+    }
+
+    fn get_chance_weight(state: &Self::State, event: Self::Chance) -> f32 {
+        let cards = 2 * RANKS as usize;
+
+        match event {
+            // A pair can be dealt two ways, and two different ranks four.
+            LeducChance::Hands([a, b]) => {
+                let ways = if a == b { 2 } else { 4 };
+                ways as f32 / (cards * (cards - 1)) as f32
+            }
+            LeducChance::Board(rank) => state.remaining(rank) as f32 / (cards - 2) as f32,
+        }
+    }
+
+    fn sample_chance<R: Rng>(state: &Self::State, rng: &mut R) -> (Self::Chance, usize) {
+        if let LeducStage::PrivateDeal = state.stage {
+            let [a, b] = random_cards(rng);
+            return (LeducChance::Hands([a, b]), (a * RANKS + b) as usize);
+        }
+
+        let mut index = rng.gen_range(0..2 * RANKS as usize - 2);
+
+        for rank in 0..RANKS {
+            let remaining = state.remaining(rank);
+            if index < remaining {
+                return (LeducChance::Board(rank), rank as usize);
+            }
+            index -= remaining;
+        }
+
+        unreachable!("there are only four cards left")
+    }
+
+    fn get_stage(state: &Self::State) -> Self::Stage {
+        state.stage
+    }
+
+    fn get_branching_hint(state: &Self::State) -> usize {
+        match state.stage {
+            LeducStage::PrivateDeal => (RANKS * RANKS) as usize,
+            LeducStage::PlayerAction(_) | LeducStage::Deal => 3,
+            LeducStage::Terminal => 0,
+        }
+    }
+
+    fn get_terminal_utilities(state: &Self::State, utilities: &mut [f32]) {
+        assert!(
+            matches!(state.stage, LeducStage::Terminal),
+            "stage must be terminal to calculate utility"
+        );
+        assert_eq!(utilities.len(), 2, "utility array is the wrong length");
+
+        let winner = if let Some(folded) = state.folded {
+            Some(1 - folded as usize)
+        } else {
+            let board = state.board.expect("no board card at showdown");
+            let strength = |p: usize| (state.cards[p] == board, state.cards[p]);
+
+            match strength(0).cmp(&strength(1)) {
+                std::cmp::Ordering::Greater => Some(0),
+                std::cmp::Ordering::Less => Some(1),
+                std::cmp::Ordering::Equal => None,
+            }
+        };
+
+        match winner {
+            Some(winner) => {
+                let loser = 1 - winner;
+                utilities[winner] = state.committed[loser] as f32;
+                utilities[loser] = -(state.committed[loser] as f32);
+            }
+            None => utilities.iter_mut().for_each(|u| *u = 0.0),
+        }
+    }
+
+    /// Actions are "f", "k", "c", "b" and "r", for fold, check, call, bet and raise, deals are each
+    /// player's rank, like "KJ", and the board card is its rank, like "K".
+    fn format_event(event: Event<Self::Action, Self::Chance>) -> String {
+        match event {
+            Event::Action(action) => match action {
+                LeducAction::Fold => "f",
+                LeducAction::Check => "k",
+                LeducAction::Call => "c",
+                LeducAction::Bet => "b",
+                LeducAction::Raise => "r",
+            }
+            .to_string(),
+            Event::Chance(LeducChance::Hands(cards)) => {
+                cards.iter().map(|&c| CARDS[c as usize]).collect()
+            }
+            Event::Chance(LeducChance::Board(rank)) => CARDS[rank as usize].to_string(),
+        }
+    }
+}
+
+impl PersistentGame for LeducGame {
+    const ID: &'static str = "leduc";
+}
+
+/// Maps the acting player's card to a parameter.  The public card is part of the public history,
+/// so it's already told apart by the node, but it's included in descriptions.
+pub struct LeducParameterMapping;
+
+impl ParameterMapping for LeducParameterMapping {
+    type State = LeducState;
+
+    fn get_parameter_count(_state: &Self::State) -> usize {
+        RANKS as usize
+    }
+
+    fn get_parameter_index(state: &Self::State) -> usize {
+        if let LeducStage::PlayerAction(player) = state.stage {
+            state.cards[player as usize] as usize
+        } else {
+            panic!("no parameter index for a non-player action stage")
+        }
+    }
+
+    fn get_parameter_description(state: &Self::State, alternate_index: Option<usize>) -> String {
+        let index = alternate_index.unwrap_or_else(|| Self::get_parameter_index(state));
+
+        assert!(index < RANKS as usize, "parameter index is out of bounds");
+
+        match state.board {
+            Some(board) => format!("{}|{}", CARDS[index], CARDS[board as usize]),
+            None => CARDS[index].to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::SeedableRng;
+
+    use game_tree::{format_history, parse_history, HistoryError};
+    use util::rng::JKiss32Rng;
+
+    use LeducAction::*;
+
+    fn act(state: &mut LeducState, action: LeducAction) {
+        let mut events = Vec::new();
+        LeducGame::populate_events(state, &mut events);
+        assert!(
+            events
+                .iter()
+                .any(|e| matches!(e, Event::Action(a) if *a == action)),
+            "{:?} is not available",
+            action
+        );
+        LeducGame::advance_state(state, Event::Action(action));
+    }
+
+    fn utilities(state: &LeducState) -> [f32; 2] {
+        let mut utilities = [0.0; 2];
+        LeducGame::get_terminal_utilities(state, &mut utilities);
+        utilities
+    }
+
+    #[test]
+    fn test_betting() {
+        let mut state = LeducState::from_cards([2, 0]);
+
+        act(&mut state, Check);
+        act(&mut state, Bet);
+        act(&mut state, Raise);
+        assert_eq!(state.committed(), [5, 3]);
+
+        // The raise cap has been reached.
+        let mut events = Vec::new();
+        LeducGame::populate_events(&state, &mut events);
+        assert_eq!(events.len(), 2);
+
+        act(&mut state, Call);
+        assert_eq!(state.stage, LeducStage::Deal);
+
+        LeducGame::advance_state(&mut state, Event::Chance(LeducChance::Board(0)));
+        assert_eq!(state.stage, LeducStage::PlayerAction(0));
+
+        act(&mut state, Bet);
+        act(&mut state, Call);
+        assert_eq!(state.stage, LeducStage::Terminal);
+        assert_eq!(state.committed(), [9, 9]);
+
+        // The jack pairs the board.
+        assert_eq!(utilities(&state), [-9.0, 9.0]);
+    }
+
+    #[test]
+    fn test_utilities() {
+        let mut state = LeducState::from_cards([2, 0]);
+        act(&mut state, Bet);
+        act(&mut state, Fold);
+        assert_eq!(utilities(&state), [1.0, -1.0]);
+
+        let mut state = LeducState::from_cards([1, 1]);
+        act(&mut state, Check);
+        act(&mut state, Check);
+        LeducGame::advance_state(&mut state, Event::Chance(LeducChance::Board(2)));
+        act(&mut state, Check);
+        act(&mut state, Check);
+        assert_eq!(utilities(&state), [0.0, 0.0]);
+
+        let mut state = LeducState::from_cards([1, 2]);
+        act(&mut state, Check);
+        act(&mut state, Check);
+        LeducGame::advance_state(&mut state, Event::Chance(LeducChance::Board(0)));
+        act(&mut state, Check);
+        act(&mut state, Check);
+        assert_eq!(utilities(&state), [-1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_chance() {
+        let mut state = LeducState::from_cards([2, 2]);
+        act(&mut state, Check);
+        act(&mut state, Check);
+
+        let weights = (0..RANKS)
+            .map(|r| LeducGame::get_chance_weight(&state, LeducChance::Board(r)))
+            .collect::<Vec<_>>();
+        assert_eq!(weights, [0.5, 0.5, 0.0]);
+
+        let mut rng = JKiss32Rng::seed_from_u64(0);
+        for _ in 0..100 {
+            let (chance, index) = LeducGame::sample_chance(&state, &mut rng);
+            assert_ne!(chance, LeducChance::Board(2), "sampled a card that's gone");
+            assert_eq!(chance, LeducChance::Board(index as u8));
+        }
+    }
+
+    #[test]
+    fn test_deal() {
+        let state = LeducState::undealt();
+
+        let mut events = Vec::new();
+        LeducGame::populate_events(&state, &mut events);
+        assert_eq!(events.len(), 9);
+
+        // Each pair of ranks is as likely as the ordered deals of the six cards that make it.
+        for event in &events {
+            let chance = match *event {
+                Event::Chance(chance) => chance,
+                _ => panic!("event must be chance"),
+            };
+            let cards = match chance {
+                LeducChance::Hands(cards) => cards,
+                _ => panic!("the players' cards must be dealt first"),
+            };
+
+            let deals = LeducState::deals()
+                .into_iter()
+                .filter(|(deal, _)| deal.cards == cards)
+                .map(|(_, probability)| probability)
+                .sum::<f32>();
+            assert!((LeducGame::get_chance_weight(&state, chance) - deals).abs() < 1e-6);
+        }
+
+        let mut rng = JKiss32Rng::seed_from_u64(0);
+        for _ in 0..100 {
+            let (chance, index) = LeducGame::sample_chance(&state, &mut rng);
+            assert_eq!(events[index], Event::Chance(chance));
+        }
+
+        let mut dealt = state;
+        LeducGame::advance_state(&mut dealt, Event::Chance(LeducChance::Hands([2, 0])));
+        assert_eq!(dealt.stage, LeducStage::PlayerAction(0));
+        assert_eq!(dealt.cards, [2, 0]);
+    }
+
+    #[test]
+    fn test_history() {
+        let root_state = LeducState::from_cards([0, 1]);
+
+        let history = [
+            Event::Action(Check),
+            Event::Action(Bet),
+            Event::Action(Call),
+            Event::Chance(LeducChance::Board(2)),
+            Event::Action(Bet),
+            Event::Action(Raise),
+            Event::Action(Fold),
+        ];
+
+        assert_eq!(format_history::<LeducGame>(&history), "k b c / K b r f");
+        assert_eq!(
+            parse_history::<LeducGame>(&root_state, "k b c / K b r f"),
+            Ok(history.to_vec())
+        );
+        assert_eq!(
+            parse_history::<LeducGame>(&root_state, " k b c/K/b  r f "),
+            Ok(history.to_vec())
+        );
+        assert_eq!(parse_history::<LeducGame>(&root_state, ""), Ok(vec![]));
+
+        let mut dealt = vec![Event::Chance(LeducChance::Hands([1, 0]))];
+        dealt.extend_from_slice(&history);
+        assert_eq!(format_history::<LeducGame>(&dealt), "QJ k b c / K b r f");
+        assert_eq!(
+            parse_history::<LeducGame>(&LeducState::undealt(), "QJ k b c / K b r f"),
+            Ok(dealt)
+        );
+
+        // Calling isn't possible before a bet, and the board can't be dealt before the round ends.
+        assert_eq!(
+            parse_history::<LeducGame>(&root_state, "c"),
+            Err(HistoryError {
+                index: 0,
+                token: "c".to_string()
+            })
+        );
+        assert_eq!(
+            parse_history::<LeducGame>(&root_state, "k K")
+                .unwrap_err()
+                .to_string(),
+            "token 1 (\"K\") isn't a legal event"
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use crate::state::{Player, State, Value};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BetSize {
+    /// A fraction of the pot after the player has called.
+    PotFraction(f32),
+    /// A fixed total commitment for the round.
+    Fixed(Value),
+    /// The pot fraction that, bet and called this many times in a row, would put the effective
+    /// stack all-in.
+    Geometric(u8),
+    AllIn,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreetSizing {
+    /// Sizes to use when there is no bet to face.
+    pub bets: Vec<BetSize>,
+    /// Sizes to use when facing a bet.
+    pub raises: Vec<BetSize>,
+    /// A size that commits at least this fraction of the player's stack becomes an all-in.
+    pub all_in_threshold: Option<f32>,
+    /// Adjacent sizes that differ by no more than this fraction of the pot (after calling) are
+    /// merged into one, keeping the smaller unless the larger is all-in.
+    pub merge_threshold: Option<f32>,
+}
+
+impl Default for StreetSizing {
+    /// A pot-sized bet or raise, and all-in.
+    fn default() -> Self {
+        Self {
+            bets: vec![BetSize::PotFraction(1.0), BetSize::AllIn],
+            raises: vec![BetSize::PotFraction(1.0), BetSize::AllIn],
+            all_in_threshold: None,
+            merge_threshold: None,
+        }
+    }
+}
+
+/// Bet sizes for each betting round.  Rounds beyond the last configured one use the last.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BetSizing {
+    rounds: Vec<StreetSizing>,
+}
+
+impl BetSizing {
+    /// Panics if `rounds` is empty.
+    pub fn new(rounds: Vec<StreetSizing>) -> Self {
+        assert!(
+            !rounds.is_empty(),
+            "must provide sizing for at least one round"
+        );
+        Self { rounds }
+    }
+
+    pub fn round(&self, round: usize) -> &StreetSizing {
+        &self.rounds[round.min(self.rounds.len() - 1)]
+    }
+
+    /// Fills `amounts` with the distinct total commitments the active player may bet or raise to,
+    /// in ascending order.  An all-in appears as `state.max_raise_to()`.  Nothing is added if the
+    /// player can't raise, or if nobody would be left to respond.
+    pub fn populate_raise_amounts<T, const N: usize>(
+        &self,
+        state: &State<T, N>,
+        round: usize,
+        amounts: &mut Vec<Value>,
+    ) {
+        amounts.clear();
+
+        let player = state.active_player;
+        let to_call = state.amount_to_call();
+
+        let responders = (0..N)
+            .map(|p| Player(p as u8))
+            .filter(|&p| p != player && state.can_act(p))
+            .count();
+
+        if state.player_stacks[player.index()] <= to_call || responders == 0 {
+            return;
+        }
+
+        let sizing = self.round(round);
+        let sizes = if state.current_bet > state.player_committed[player.index()] {
+            &sizing.raises
+        } else {
+            &sizing.bets
+        };
+
+        let pot = (state.current_pot() + to_call) as f32;
+        let max = state.max_raise_to();
+        let min = state.min_raise_to().min(max);
+
+        for &size in sizes {
+            let amount = match size {
+                BetSize::PotFraction(f) => state.current_bet + (f * pot).round() as Value,
+                BetSize::Fixed(amount) => amount,
+                BetSize::Geometric(bets) => {
+                    let stack = effective_stack(state) as f32;
+                    let f = ((1.0 + 2.0 * stack / pot).powf(1.0 / bets as f32) - 1.0) / 2.0;
+                    state.current_bet + (f * pot).round() as Value
+                }
+                BetSize::AllIn => max,
+            };
+
+            let mut amount = amount.clamp(min, max);
+
+            if let Some(threshold) = sizing.all_in_threshold {
+                let committed = state.player_committed[player.index()];
+                let stack = max - committed;
+                if (amount - committed) as f32 >= threshold * stack as f32 {
+                    amount = max;
+                }
+            }
+
+            amounts.push(amount);
+        }
+
+        amounts.sort_unstable();
+        amounts.dedup();
+
+        if let Some(threshold) = sizing.merge_threshold {
+            let mut i = 1;
+            while i < amounts.len() {
+                if (amounts[i] - amounts[i - 1]) as f32 <= threshold * pot {
+                    if amounts[i] == max {
+                        amounts.remove(i - 1);
+                    } else {
+                        amounts.remove(i);
+                    }
+                } else {
+                    i += 1;
+                }
+            }
+        }
+    }
+}
+
+impl Default for BetSizing {
+    fn default() -> Self {
+        Self::new(vec![StreetSizing::default()])
+    }
+}
+
+/// The most the active player could get called for, beyond the current bet.
+fn effective_stack<T, const N: usize>(state: &State<T, N>) -> Value {
+    let remaining = |p: usize| {
+        let to_call = state.current_bet - state.player_committed[p].min(state.current_bet);
+        state.player_stacks[p].saturating_sub(to_call)
+    };
+
+    let player = state.active_player.index();
+
+    let opponents = (0..N)
+        .filter(|&p| p != player && state.can_act(Player(p as u8)))
+        .map(remaining)
+        .max()
+        .unwrap_or(0);
+
+    remaining(player).min(opponents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(stacks: [Value; 2], committed: [Value; 2], pot: Value) -> State<(), 2> {
+        let current_bet = committed[0].max(committed[1]);
+        State {
+            player_stacks: stacks,
+            player_committed: committed,
+            player_folded: [false; 2],
+            active_player: Player(0),
+            last_aggressor: None,
+            pot,
+            last_raise: 2,
+            current_bet,
+            game_data: (),
+        }
+    }
+
+    fn sizing(bets: Vec<BetSize>, raises: Vec<BetSize>) -> StreetSizing {
+        StreetSizing {
+            bets,
+            raises,
+            all_in_threshold: None,
+            merge_threshold: None,
+        }
+    }
+
+    fn amounts(sizing: StreetSizing, state: &State<(), 2>) -> Vec<Value> {
+        let mut amounts = Vec::new();
+        BetSizing::new(vec![sizing]).populate_raise_amounts(state, 0, &mut amounts);
+        amounts
+    }
+
+    #[test]
+    fn test_pot_fractions() {
+        let sizing = sizing(
+            vec![
+                BetSize::PotFraction(0.5),
+                BetSize::PotFraction(1.0),
+                BetSize::AllIn,
+            ],
+            vec![BetSize::PotFraction(1.0), BetSize::Fixed(50)],
+        );
+
+        assert_eq!(
+            amounts(sizing.clone(), &state([100, 100], [0, 0], 20)),
+            [10, 20, 100]
+        );
+
+        // Facing a bet of 10 into 20, a pot raise is to 10 + 40.
+        assert_eq!(amounts(sizing, &state([100, 90], [0, 10], 20)), [50]);
+    }
+
+    #[test]
+    fn test_minimum_raise() {
+        let sizing = sizing(vec![], vec![BetSize::PotFraction(0.1), BetSize::Fixed(5)]);
+
+        let mut state = state([100, 80], [0, 20], 10);
+        state.last_raise = 20;
+
+        assert_eq!(amounts(sizing, &state), [40]);
+    }
+
+    #[test]
+    fn test_all_in_threshold() {
+        let mut sizing = sizing(
+            vec![BetSize::PotFraction(0.5), BetSize::PotFraction(1.0)],
+            vec![],
+        );
+        sizing.all_in_threshold = Some(0.6);
+
+        assert_eq!(
+            amounts(sizing.clone(), &state([50, 50], [0, 0], 40)),
+            [20, 50]
+        );
+
+        // Nothing bigger than the stack.
+        sizing.all_in_threshold = None;
+        assert_eq!(amounts(sizing, &state([30, 50], [0, 0], 40)), [20, 30]);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut sizing = sizing(
+            vec![
+                BetSize::PotFraction(0.5),
+                BetSize::PotFraction(0.6),
+                BetSize::PotFraction(1.0),
+                BetSize::PotFraction(1.1),
+                BetSize::AllIn,
+            ],
+            vec![],
+        );
+        sizing.merge_threshold = Some(0.2);
+
+        assert_eq!(amounts(sizing, &state([110, 200], [0, 0], 100)), [50, 110]);
+    }
+
+    #[test]
+    fn test_geometric() {
+        let sizing = sizing(vec![BetSize::Geometric(1), BetSize::Geometric(2)], vec![]);
+
+        // Two pot-sized bets get 200 all-in with a pot of 50.
+        assert_eq!(amounts(sizing, &state([200, 300], [0, 0], 50)), [50, 200]);
+    }
+
+    #[test]
+    fn test_no_responders() {
+        let sizing = sizing(vec![BetSize::AllIn], vec![BetSize::AllIn]);
+
+        let mut state = state([100, 0], [0, 30], 10);
+        assert_eq!(amounts(sizing.clone(), &state), []);
+
+        state.player_stacks[1] = 10;
+        assert_eq!(amounts(sizing, &state), [100]);
+    }
+}
@@ -1,8 +1,11 @@
+use std::convert::TryFrom;
 use std::fmt;
 use std::mem;
+use std::str::FromStr;
 
 use util::math::ncr;
 
+use crate::parse::ParseError;
 use crate::rank::Rank;
 use crate::suit::Suit;
 
@@ -54,6 +57,52 @@ impl fmt::Debug for Card {
     }
 }
 
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl FromStr for Card {
+    type Err = ParseError;
+
+    /// Parses a rank followed by a suit, e.g. "As" or "Td".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseError::new(s, 0, "invalid card");
+
+        if !s.is_ascii() || s.len() != 2 {
+            return Err(invalid());
+        }
+
+        let rank = s[..1].parse::<Rank>().map_err(|_| invalid())?;
+        let suit = s[1..].parse::<Suit>().map_err(|_| invalid())?;
+
+        Ok(Card::from_rank_and_suit(rank, suit))
+    }
+}
+
+/// Parses a run of cards with no separators, e.g. "QhJh9c".
+pub fn parse_cards(s: &str) -> Result<Vec<Card>, ParseError> {
+    if !s.is_ascii() {
+        return Err(ParseError::new(s, 0, "invalid card"));
+    }
+
+    let mut cards = Vec::with_capacity(s.len() / 2);
+
+    for i in (0..s.len()).step_by(2) {
+        let token = &s[i..(i + 2).min(s.len())];
+        let card = token.parse::<Card>().map_err(|e| e.offset(i))?;
+
+        if cards.contains(&card) {
+            return Err(ParseError::new(token, i, "duplicate card"));
+        }
+
+        cards.push(card);
+    }
+
+    Ok(cards)
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct CardRange {
     len: u8,
@@ -69,7 +118,7 @@ impl CardRange {
         let end = card_index(*cards.iter().max().unwrap());
 
         let mut range = Self {
-            len: cards.len() as u8,
+            len: 0,
             start,
             end,
             present: 0,
@@ -100,7 +149,8 @@ impl CardRange {
     }
 
     pub fn contains(&self, card: Card) -> bool {
-        self.present & 1u64 << (card_index(card) - self.start) == 1
+        let i = card_index(card);
+        i >= self.start && i <= self.end && self.present & 1u64 << (i - self.start) != 0
     }
 
     /// Returns true if the card was successfully added to the range.
@@ -109,8 +159,11 @@ impl CardRange {
         let i = card_index(card);
         if i >= self.start && i <= self.end {
             let mask = 1u64 << (i - self.start);
-            let exists = self.present & mask == 1;
-            self.present |= mask;
+            let exists = self.present & mask != 0;
+            if !exists {
+                self.present |= mask;
+                self.len += 1;
+            }
             !exists
         } else {
             false
@@ -123,8 +176,11 @@ impl CardRange {
         let i = card_index(card);
         if i >= self.start && i <= self.end {
             let mask = 1u64 << (i - self.start);
-            let exists = self.present & mask == 1;
-            self.present &= !mask;
+            let exists = self.present & mask != 0;
+            if exists {
+                self.present &= !mask;
+                self.len -= 1;
+            }
             exists
         } else {
             false
@@ -135,10 +191,9 @@ impl CardRange {
     pub fn index_of(&self, card: Card) -> Option<usize> {
         let i = card_index(card);
 
-        (i >= self.start && i <= self.end && self.present & 1u64 << i == 1).then(|| {
-            let mask = 0xFFFFFFFFFFFFFFFF >> (63 - i);
-            let offset = (self.present & mask).count_zeros() as usize;
-            i as usize - offset
+        self.contains(card).then(|| {
+            let mask = (1u64 << (i - self.start)) - 1;
+            (self.present & mask).count_ones() as usize
         })
     }
 
@@ -159,6 +214,52 @@ impl CardRange {
     }
 }
 
-fn card_index(card: Card) -> u8 {
+pub(crate) fn card_index(card: Card) -> u8 {
     ((card.suit() as u8 >> 4) - 1) * 13 + (card.rank() as u8 - 2)
 }
+
+pub(crate) fn card_from_index(index: u8) -> Card {
+    assert!(index < 52, "card index out of range");
+    Card::from_rank_and_suit(
+        Rank::try_from(index % 13 + 2).unwrap(),
+        Suit::try_from((index / 13 + 1) << 4).unwrap(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_card_strings() {
+        for i in 0..52 {
+            let card = card_from_index(i);
+            assert_eq!(card.to_string().parse::<Card>(), Ok(card));
+        }
+
+        assert_eq!("tD".parse::<Card>(), Ok(Card::DT));
+        assert!("Ax".parse::<Card>().is_err());
+        assert!("A".parse::<Card>().is_err());
+        assert!("Asd".parse::<Card>().is_err());
+    }
+
+    #[test]
+    fn test_parse_cards() {
+        assert_eq!(
+            parse_cards("QhJh9c"),
+            Ok(vec![Card::HQ, Card::HJ, Card::C9])
+        );
+
+        let error = parse_cards("QhJx9c").unwrap_err();
+        assert_eq!((error.token.as_str(), error.position), ("Jx", 2));
+
+        let error = parse_cards("QhJh9").unwrap_err();
+        assert_eq!((error.token.as_str(), error.position), ("9", 4));
+
+        let error = parse_cards("QhJhQh").unwrap_err();
+        assert_eq!(
+            (error.token.as_str(), error.position, error.reason),
+            ("Qh", 4, "duplicate card")
+        );
+    }
+}
@@ -40,7 +40,8 @@ impl<const N: usize> Deck<N> {
     }
 
     pub fn shuffle<R: Rng>(&mut self, rng: &mut R) {
-        self.cards.shuffle(rng);
+        let len = self.len();
+        self.cards[..len].shuffle(rng);
     }
 
     pub fn deal_card(&mut self) -> Card {
@@ -61,4 +62,21 @@ impl<const N: usize> Deck<N> {
     pub fn contains(&self, card: Card) -> bool {
         self.range.contains(card)
     }
+
+    /// Returns true if the card was present and has been removed from the deck.
+    pub fn remove(&mut self, card: Card) -> bool {
+        if let Some(i) = self.cards().iter().position(|&c| c == card) {
+            // Keep the remaining cards at the front of the array.
+            let last = self.len() - 1;
+            self.cards.swap(i, last);
+            self.range.remove(card)
+        } else {
+            false
+        }
+    }
+
+    /// The cards remaining in the deck, in dealing order from the back.
+    pub fn cards(&self) -> &[Card] {
+        &self.cards[..self.len()]
+    }
 }
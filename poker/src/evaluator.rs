@@ -0,0 +1,283 @@
+use std::fmt;
+use std::mem;
+
+use crate::card::Card;
+use crate::hand::{Hand, HandComparator};
+
+/// The category of a five-card poker hand, in ascending order of strength.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[repr(u8)]
+pub enum HandClass {
+    HighCard,
+    Pair,
+    TwoPair,
+    ThreeOfAKind,
+    Straight,
+    Flush,
+    FullHouse,
+    FourOfAKind,
+    StraightFlush,
+}
+
+/// The strength of the best five-card hand that can be made from a set of cards.
+/// Ranks compare first by class, then by the ranks that make up the class, then by kickers.
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct HandRank(u32);
+
+impl HandRank {
+    /// `primary` holds the rank bits that define the class (the quads, the trips of a full house,
+    /// both pairs of a two pair, etc.), and `secondary` holds the rank bits that break ties.
+    fn new(class: HandClass, primary: u16, secondary: u16) -> Self {
+        Self((class as u32) << 26 | (primary as u32) << 13 | secondary as u32)
+    }
+
+    pub fn class(self) -> HandClass {
+        unsafe { mem::transmute::<u8, HandClass>((self.0 >> 26) as u8) }
+    }
+}
+
+impl fmt::Debug for HandRank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}({:#010x})", self.class(), self.0 & 0x3FFFFFF)
+    }
+}
+
+/// Evaluates hands of five to seven cards using per-suit rank bitmasks.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HandEvaluator;
+
+impl HandEvaluator {
+    /// Panics if `cards` does not contain between five and seven cards.
+    pub fn evaluate(cards: &[Card]) -> HandRank {
+        assert!(
+            (5..=7).contains(&cards.len()),
+            "can only evaluate hands of five to seven cards"
+        );
+
+        let mut suits = [0u16; 4];
+        let mut counts = [0u8; 13];
+
+        for &card in cards {
+            let rank = card.rank() as usize - 2;
+            suits[(card.suit() as usize >> 4) - 1] |= 1 << rank;
+            counts[rank] += 1;
+        }
+
+        let ranks = suits.iter().fold(0, |a, &s| a | s);
+
+        let (mut pairs, mut trips, mut quads) = (0u16, 0u16, 0u16);
+        for (i, &count) in counts.iter().enumerate() {
+            match count {
+                2 => pairs |= 1 << i,
+                3 => trips |= 1 << i,
+                4 => quads |= 1 << i,
+                _ => (),
+            }
+        }
+
+        let flush = suits.iter().copied().find(|s| s.count_ones() >= 5);
+
+        if let Some(top) = flush.and_then(straight_top) {
+            return HandRank::new(HandClass::StraightFlush, top, 0);
+        }
+
+        if quads != 0 {
+            let quad = highest(quads, 1);
+            return HandRank::new(HandClass::FourOfAKind, quad, highest(ranks & !quad, 1));
+        }
+
+        if trips != 0 {
+            let trip = highest(trips, 1);
+            let pair = (trips & !trip) | pairs;
+            if pair != 0 {
+                return HandRank::new(HandClass::FullHouse, trip, highest(pair, 1));
+            }
+        }
+
+        if let Some(flush) = flush {
+            return HandRank::new(HandClass::Flush, highest(flush, 5), 0);
+        }
+
+        if let Some(top) = straight_top(ranks) {
+            return HandRank::new(HandClass::Straight, top, 0);
+        }
+
+        if trips != 0 {
+            let trip = highest(trips, 1);
+            return HandRank::new(HandClass::ThreeOfAKind, trip, highest(ranks & !trip, 2));
+        }
+
+        if pairs.count_ones() >= 2 {
+            let two_pair = highest(pairs, 2);
+            return HandRank::new(HandClass::TwoPair, two_pair, highest(ranks & !two_pair, 1));
+        }
+
+        if pairs != 0 {
+            return HandRank::new(HandClass::Pair, pairs, highest(ranks & !pairs, 3));
+        }
+
+        HandRank::new(HandClass::HighCard, highest(ranks, 5), 0)
+    }
+}
+
+impl HandComparator<5> for HandEvaluator {
+    type HandRank = HandRank;
+
+    fn hand_rank(&self, hand: &Hand<5>) -> Self::HandRank {
+        Self::evaluate(hand)
+    }
+}
+
+impl HandComparator<7> for HandEvaluator {
+    type HandRank = HandRank;
+
+    fn hand_rank(&self, hand: &Hand<7>) -> Self::HandRank {
+        Self::evaluate(hand)
+    }
+}
+
+/// Keeps only the `n` highest set bits of `mask`.
+fn highest(mut mask: u16, n: u32) -> u16 {
+    while mask.count_ones() > n {
+        mask &= mask - 1;
+    }
+    mask
+}
+
+/// Returns the bit of the highest rank of the best straight in `mask`, if there is one.
+/// The ace also plays low, so the wheel's top rank is the five.
+fn straight_top(mask: u16) -> Option<u16> {
+    // Shift up by one so that bit 0 can hold the low ace.
+    let m = (mask as u32) << 1 | (mask as u32 >> 12) & 1;
+    let straights = m & m << 1 & m << 2 & m << 3 & m << 4;
+
+    (straights != 0).then(|| 1 << (31 - straights.leading_zeros() - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::convert::TryFrom;
+
+    use rand::seq::index::sample;
+    use rand::SeedableRng;
+
+    use util::rng::JKiss32Rng;
+
+    use crate::rank::Rank;
+    use crate::suit::Suit;
+
+    fn all_cards() -> Vec<Card> {
+        let mut cards = Vec::with_capacity(52);
+        for s in (0x10..=0x40).step_by(0x10) {
+            for r in 2..=14 {
+                cards.push(Card::from_rank_and_suit(
+                    Rank::try_from(r).unwrap(),
+                    Suit::try_from(s).unwrap(),
+                ));
+            }
+        }
+        cards
+    }
+
+    #[test]
+    fn test_five_card_class_counts() {
+        let cards = all_cards();
+
+        let mut counts = [0usize; 9];
+        let mut ranks = Vec::with_capacity(2598960);
+
+        for a in 0..52 {
+            for b in a + 1..52 {
+                for c in b + 1..52 {
+                    for d in c + 1..52 {
+                        for e in d + 1..52 {
+                            let hand = [cards[a], cards[b], cards[c], cards[d], cards[e]];
+                            let rank = HandEvaluator.hand_rank(&hand);
+                            counts[rank.class() as usize] += 1;
+                            ranks.push(rank);
+                        }
+                    }
+                }
+            }
+        }
+
+        assert_eq!(
+            counts,
+            [1302540, 1098240, 123552, 54912, 10200, 5108, 3744, 624, 40],
+            "incorrect hand class counts"
+        );
+
+        ranks.sort_unstable();
+        ranks.dedup();
+        assert_eq!(ranks.len(), 7462, "incorrect number of distinct hand ranks");
+    }
+
+    #[test]
+    fn test_hand_ordering() {
+        use Card::*;
+
+        let rank = |hand: [Card; 5]| HandEvaluator.hand_rank(&hand);
+
+        // The wheel is the lowest straight.
+        let wheel = rank([SA, C2, D3, H4, S5]);
+        assert_eq!(wheel.class(), HandClass::Straight);
+        assert!(wheel < rank([C2, D3, H4, S5, C6]));
+        assert!(rank([SA, SK, SQ, SJ, ST]) > rank([S9, SK, SQ, SJ, ST]));
+
+        // The steel wheel is the lowest straight flush.
+        let steel_wheel = rank([HA, H2, H3, H4, H5]);
+        assert_eq!(steel_wheel.class(), HandClass::StraightFlush);
+        assert!(steel_wheel < rank([H2, H3, H4, H5, H6]));
+        assert!(steel_wheel > rank([CA, DA, HA, SA, CK]));
+
+        // Kickers.
+        assert!(rank([CA, DA, H9, S8, C2]) > rank([HA, SA, H9, S7, C6]));
+        assert!(rank([CK, DK, H9, S9, C3]) > rank([HK, SK, C9, D9, C2]));
+        assert!(rank([CK, DK, H9, S9, C3]) < rank([HK, SK, CT, DT, C2]));
+        assert!(rank([C7, D7, H7, S9, C2]) > rank([C6, D6, H6, SA, CK]));
+        assert!(rank([C7, D7, H7, S2, C2]) < rank([C7, D7, H7, S3, C3]));
+        assert_eq!(rank([C7, D7, H7, S2, C2]), rank([C7, S7, H7, D2, C2]));
+
+        // Flush beats straight, and flushes compare by every card.
+        assert!(rank([C2, C4, C6, C8, CT]) > rank([CA, DK, HQ, SJ, CT]));
+        assert!(rank([CA, CK, CQ, CJ, C9]) > rank([DA, DK, DQ, DJ, D8]));
+    }
+
+    #[test]
+    fn test_seven_card_best_five() {
+        let cards = all_cards();
+        let mut rng = JKiss32Rng::seed_from_u64(0);
+
+        for _ in 0..20000 {
+            let mut hand = [Card::C2; 7];
+            for (h, i) in hand.iter_mut().zip(sample(&mut rng, 52, 7)) {
+                *h = cards[i];
+            }
+
+            let mut best = None;
+            for skip_a in 0..7 {
+                for skip_b in skip_a + 1..7 {
+                    let mut five = [Card::C2; 5];
+                    for (f, &c) in five.iter_mut().zip(
+                        hand.iter()
+                            .enumerate()
+                            .filter(|&(i, _)| i != skip_a && i != skip_b)
+                            .map(|(_, c)| c),
+                    ) {
+                        *f = c;
+                    }
+                    best = best.max(Some(HandEvaluator.hand_rank(&five)));
+                }
+            }
+
+            assert_eq!(
+                HandEvaluator.hand_rank(&hand),
+                best.unwrap(),
+                "seven card rank doesn't match the best five cards of {:?}",
+                hand,
+            );
+        }
+    }
+}
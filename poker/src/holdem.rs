@@ -0,0 +1,903 @@
+use std::sync::Arc;
+
+use rand::seq::index::sample;
+use rand::Rng;
+
+use game_tree::{Event, Game, ParameterMapping, Stage, SubtreeKey};
+use util::math::ncr;
+
+use crate::betting::BetSizing;
+use crate::card::Card;
+use crate::deck::{full_deck, Deck};
+use crate::evaluator::HandEvaluator;
+use crate::hand::HandComparator;
+use crate::range::HoleCardRange;
+use crate::state::{Player, State, Value};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Street {
+    Preflop,
+    Flop,
+    Turn,
+    River,
+}
+
+impl Street {
+    pub fn next(self) -> Option<Self> {
+        match self {
+            Street::Preflop => Some(Street::Flop),
+            Street::Flop => Some(Street::Turn),
+            Street::Turn => Some(Street::River),
+            Street::River => None,
+        }
+    }
+
+    /// The number of board cards showing once this street has been dealt.
+    pub fn board_size(self) -> usize {
+        match self {
+            Street::Preflop => 0,
+            Street::Flop => 3,
+            Street::Turn => 4,
+            Street::River => 5,
+        }
+    }
+
+    fn previous_board_size(self) -> usize {
+        match self {
+            Street::Preflop | Street::Flop => 0,
+            Street::Turn => 3,
+            Street::River => 4,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum HoldemStage {
+    PlayerAction(u8),
+    Deal,
+    Terminal,
+}
+
+impl Stage for HoldemStage {
+    fn is_action(&self) -> bool {
+        matches!(self, HoldemStage::PlayerAction(_))
+    }
+
+    fn is_chance(&self) -> bool {
+        matches!(self, HoldemStage::Deal)
+    }
+
+    fn is_terminal(&self) -> bool {
+        matches!(self, HoldemStage::Terminal)
+    }
+
+    fn player_to_act(&self) -> Option<usize> {
+        match self {
+            HoldemStage::PlayerAction(p) => Some(*p as usize),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HoldemAction {
+    Fold,
+    Check,
+    Call,
+    /// Bet so that the player's total commitment for the round is the given amount.
+    Bet(Value),
+    /// Raise so that the player's total commitment for the round is the given amount.
+    Raise(Value),
+    AllIn,
+}
+
+#[derive(Clone, Debug)]
+pub struct HoldemConfig<const N: usize> {
+    /// Starting stacks, before blinds are posted.  Player 0 posts the small blind, and player 1
+    /// the big blind.
+    pub stacks: [Value; N],
+    pub small_blind: Value,
+    pub big_blind: Value,
+    /// Betting rounds are indexed by `Street`.
+    pub bet_sizing: BetSizing,
+}
+
+#[derive(Clone, Debug)]
+pub struct HoldemData<const N: usize> {
+    pub street: Street,
+    pub stage: HoldemStage,
+    pub hole_cards: [[Card; 2]; N],
+    pub deck: Deck<52>,
+    pub starting_stacks: [Value; N],
+    pub big_blind: Value,
+    pub bet_sizing: Arc<BetSizing>,
+    board: [Card; 5],
+    board_len: u8,
+    /// Whether each player has acted since the last full raise.  A player who has, and is facing a
+    /// short all-in, can only call or fold.
+    acted: [bool; N],
+}
+
+impl<const N: usize> HoldemData<N> {
+    pub fn board(&self) -> &[Card] {
+        &self.board[..self.board_len as usize]
+    }
+}
+
+pub type HoldemState<const N: usize> = State<HoldemData<N>, N>;
+
+impl<const N: usize> State<HoldemData<N>, N> {
+    /// Posts the blinds and sets up the first preflop decision.
+    pub fn from_cards(config: &HoldemConfig<N>, hole_cards: [[Card; 2]; N]) -> Self {
+        assert!(N >= 2, "holdem requires at least two players");
+
+        let mut deck = full_deck();
+        for &card in hole_cards.iter().flatten() {
+            assert!(deck.remove(card), "duplicate hole card");
+        }
+
+        let mut state = State {
+            player_stacks: config.stacks,
+            player_committed: [0; N],
+            player_folded: [false; N],
+            active_player: Player(0),
+            last_aggressor: None,
+            pot: 0,
+            last_raise: config.big_blind,
+            current_bet: 0,
+            game_data: HoldemData {
+                street: Street::Preflop,
+                stage: HoldemStage::PlayerAction(0),
+                hole_cards,
+                deck,
+                starting_stacks: config.stacks,
+                big_blind: config.big_blind,
+                bet_sizing: Arc::new(config.bet_sizing.clone()),
+                board: [Card::C2; 5],
+                board_len: 0,
+                acted: [false; N],
+            },
+        };
+
+        state.commit_to(config.small_blind);
+        state.active_player = Player(1);
+        state.commit_to(config.big_blind);
+        state.last_raise = config.big_blind;
+        state.last_aggressor = None;
+
+        finish_action(&mut state);
+
+        state
+    }
+
+    pub fn random<R: Rng>(config: &HoldemConfig<N>, rng: &mut R) -> Self {
+        let mut deck = full_deck();
+        deck.shuffle(rng);
+
+        let mut hole_cards = [[Card::C2; 2]; N];
+        for cards in hole_cards.iter_mut() {
+            deck.deal_cards(cards);
+        }
+
+        Self::from_cards(config, hole_cards)
+    }
+}
+
+/// No-limit Texas Hold'em.  Hole cards are part of the root state, and the board is dealt by
+/// chance nodes, one card at a time.  The cards of each street are dealt in descending order so
+/// that each distinct board appears only once in the tree.
+pub struct HoldemGame<const N: usize>;
+
+impl<const N: usize> Game for HoldemGame<N> {
+    type Action = HoldemAction;
+    type Chance = Card;
+    type ParameterMapping = HoldemParameterMapping<N>;
+    type Stage = HoldemStage;
+    type State = HoldemState<N>;
+
+    fn advance_state(state: &mut Self::State, event: Event<Self::Action, Self::Chance>) {
+        match event {
+            Event::Action(action) => {
+                let player = if let HoldemStage::PlayerAction(player) = state.game_data.stage {
+                    player as usize
+                } else {
+                    panic!("cannot apply an action outside of a player action stage");
+                };
+
+                let min_raise_to = state.min_raise_to();
+                let raised = match action {
+                    HoldemAction::Fold => {
+                        state.player_folded[player] = true;
+                        false
+                    }
+                    HoldemAction::Check => {
+                        assert_eq!(state.amount_to_call(), 0, "cannot check facing a bet");
+                        false
+                    }
+                    HoldemAction::Call => state.commit_to(state.current_bet),
+                    HoldemAction::Bet(amount) | HoldemAction::Raise(amount) => {
+                        state.commit_to(amount)
+                    }
+                    HoldemAction::AllIn => state.commit_to(state.max_raise_to()),
+                };
+
+                // A short all-in doesn't reopen the betting to players who have already acted.
+                let acted = &mut state.game_data.acted;
+                if raised && state.current_bet >= min_raise_to {
+                    *acted = [false; N];
+                }
+                acted[player] = true;
+
+                finish_action(state);
+            }
+            Event::Chance(card) => {
+                assert!(
+                    matches!(state.game_data.stage, HoldemStage::Deal),
+                    "cannot deal a card outside of a deal stage"
+                );
+
+                let data = &mut state.game_data;
+                assert!(data.deck.remove(card), "card is not in the deck");
+                data.board[data.board_len as usize] = card;
+                data.board_len += 1;
+
+                if data.board_len as usize == data.street.board_size() {
+                    finish_deal(state);
+                }
+            }
+        }
+    }
+
+    fn populate_events(state: &Self::State, events: &mut Vec<Event<Self::Action, Self::Chance>>) {
+        events.clear();
+
+        match state.game_data.stage {
+            HoldemStage::PlayerAction(_) => populate_actions(state, events),
+            HoldemStage::Deal => {
+                let (mut candidates, remaining) = deal_candidates(state);
+                // The lowest cards can't be the highest card of the street.
+                candidates.truncate(candidates.len() + 1 - remaining);
+                events.extend(candidates.into_iter().map(Event::Chance));
+            }
+            HoldemStage::Terminal => (),
+        }
+    }
+
+    fn get_chance_weight(state: &Self::State, event: Self::Chance) -> f32 {
+        let (candidates, remaining) = deal_candidates(state);
+        let below = candidates.iter().filter(|&&c| c < event).count();
+
+        ncr(below, remaining - 1) as f32 / ncr(candidates.len(), remaining) as f32
+    }
+
+    fn sample_chance<R: Rng>(state: &Self::State, rng: &mut R) -> (Self::Chance, usize) {
+        let (candidates, remaining) = deal_candidates(state);
+
+        // The highest of the cards dealt this street comes first, and candidates are in
+        // descending order.
+        let index = sample(rng, candidates.len(), remaining)
+            .into_iter()
+            .min()
+            .unwrap();
+
+        (candidates[index], index)
+    }
+
+    fn get_stage(state: &Self::State) -> Self::Stage {
+        state.game_data.stage
+    }
+
+    fn get_branching_hint(state: &Self::State) -> usize {
+        match state.game_data.stage {
+            HoldemStage::PlayerAction(_) => 4,
+            HoldemStage::Deal => state.game_data.deck.len(),
+            HoldemStage::Terminal => 0,
+        }
+    }
+
+    fn get_terminal_utilities(state: &Self::State, utilities: &mut [f32]) {
+        assert!(
+            matches!(state.game_data.stage, HoldemStage::Terminal),
+            "stage must be terminal to calculate utility"
+        );
+        assert_eq!(utilities.len(), N, "utility array is the wrong length");
+
+        let data = &state.game_data;
+
+        let mut remaining = [0; N];
+        for (i, r) in remaining.iter_mut().enumerate() {
+            *r = data.starting_stacks[i] - state.player_stacks[i];
+            utilities[i] = -(*r as f32);
+        }
+
+        if state.active_player_count() == 1 {
+            let winner = state.player_folded.iter().position(|&f| !f).unwrap();
+            utilities[winner] += remaining.iter().sum::<Value>() as f32;
+            return;
+        }
+
+        assert_eq!(
+            data.board().len(),
+            5,
+            "board must be complete for a showdown"
+        );
+
+        let ranks = (0..N)
+            .map(|p| {
+                let mut hand = [Card::C2; 7];
+                hand[..2].copy_from_slice(&data.hole_cards[p]);
+                hand[2..].copy_from_slice(data.board());
+                HandEvaluator.hand_rank(&hand)
+            })
+            .collect::<Vec<_>>();
+
+        // Award each side pot, starting from the smallest all-in.
+        loop {
+            let contenders = (0..N)
+                .filter(|&p| !state.player_folded[p] && remaining[p] > 0)
+                .collect::<Vec<_>>();
+
+            let level = match contenders.iter().map(|&p| remaining[p]).min() {
+                Some(level) => level,
+                None => break,
+            };
+
+            let mut pot = 0;
+            for r in remaining.iter_mut() {
+                let contribution = (*r).min(level);
+                pot += contribution;
+                *r -= contribution;
+            }
+
+            let best = contenders.iter().map(|&p| ranks[p]).max().unwrap();
+            let winners = contenders
+                .into_iter()
+                .filter(|&p| ranks[p] == best)
+                .collect::<Vec<_>>();
+
+            for &w in &winners {
+                utilities[w] += pot as f32 / winners.len() as f32;
+            }
+        }
+
+        // Anything left over was never matched, so it goes back to whoever put it in.
+        for (u, &r) in utilities.iter_mut().zip(remaining.iter()) {
+            *u += r as f32;
+        }
+    }
+
+    /// Actions are "f", "k", "c" and "a" for fold, check, call and all-in, and "b" or "r" with the
+    /// amount, like "r250", for bets and raises.  Cards are written like "As".
+    fn format_event(event: Event<Self::Action, Self::Chance>) -> String {
+        match event {
+            Event::Action(action) => match action {
+                HoldemAction::Fold => "f".to_string(),
+                HoldemAction::Check => "k".to_string(),
+                HoldemAction::Call => "c".to_string(),
+                HoldemAction::Bet(amount) => format!("b{}", amount),
+                HoldemAction::Raise(amount) => format!("r{}", amount),
+                HoldemAction::AllIn => "a".to_string(),
+            },
+            Event::Chance(card) => card.to_string(),
+        }
+    }
+}
+
+/// Checks whether the betting round is over and moves to the next decision or stage.
+fn finish_action<const N: usize>(state: &mut HoldemState<N>) {
+    if state.active_player_count() == 1 {
+        state.game_data.stage = HoldemStage::Terminal;
+        return;
+    }
+
+    let acting_players = state.acting_player_count();
+
+    let round_complete = (0..N)
+        .map(|p| Player(p as u8))
+        .filter(|&p| state.can_act(p))
+        .all(|p| {
+            state.player_committed[p.index()] == state.current_bet
+                && (state.game_data.acted[p.index()] || acting_players == 1)
+        });
+
+    if round_complete {
+        finish_round(state);
+    } else {
+        state.active_player = state.next_acting_player().unwrap();
+        state.game_data.stage = HoldemStage::PlayerAction(state.active_player.0);
+    }
+}
+
+fn finish_round<const N: usize>(state: &mut HoldemState<N>) {
+    state.collect_bets();
+
+    if let Some(street) = state.game_data.street.next() {
+        state.game_data.street = street;
+        state.game_data.stage = HoldemStage::Deal;
+    } else {
+        state.game_data.stage = HoldemStage::Terminal;
+    }
+}
+
+fn finish_deal<const N: usize>(state: &mut HoldemState<N>) {
+    // Nobody is left to bet, so just run out the board.
+    if state.acting_player_count() < 2 {
+        finish_round(state);
+        return;
+    }
+
+    state.last_raise = state.game_data.big_blind;
+    state.last_aggressor = None;
+    state.game_data.acted = [false; N];
+
+    // Heads-up, the big blind acts first after the flop.
+    let first = Player(if N == 2 { 1 } else { 0 });
+    state.active_player = state.first_acting_player_from(first).unwrap();
+    state.game_data.stage = HoldemStage::PlayerAction(state.active_player.0);
+}
+
+fn populate_actions<const N: usize>(
+    state: &HoldemState<N>,
+    events: &mut Vec<Event<HoldemAction, Card>>,
+) {
+    if state.amount_to_call() > 0 {
+        events.push(Event::Action(HoldemAction::Fold));
+        events.push(Event::Action(HoldemAction::Call));
+    } else {
+        events.push(Event::Action(HoldemAction::Check));
+    }
+
+    if state.game_data.acted[state.active_player.index()] {
+        return;
+    }
+
+    let mut amounts = Vec::new();
+    state.game_data.bet_sizing.populate_raise_amounts(
+        state,
+        state.game_data.street as usize,
+        &mut amounts,
+    );
+
+    events.extend(amounts.into_iter().map(|amount| {
+        Event::Action(if amount == state.max_raise_to() {
+            HoldemAction::AllIn
+        } else if state.current_bet == 0 {
+            HoldemAction::Bet(amount)
+        } else {
+            HoldemAction::Raise(amount)
+        })
+    }));
+}
+
+/// Everything about a state that shapes the tree below it: the betting so far, and how many cards
+/// can still be dealt, but not which ones.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct HoldemSubtreeKey<const N: usize> {
+    street: Street,
+    stage: HoldemStage,
+    board_len: u8,
+    /// The number of cards the next card dealt could be, at deal stages.
+    candidates: usize,
+    player_stacks: [Value; N],
+    player_committed: [Value; N],
+    player_folded: [bool; N],
+    active_player: Player,
+    last_aggressor: Option<Player>,
+    pot: Value,
+    last_raise: Value,
+    current_bet: Value,
+    acted: [bool; N],
+}
+
+/// Boards only change which cards can be dealt next, not how many, so the betting after every
+/// deal of a street shares a key.
+impl<const N: usize> SubtreeKey for HoldemGame<N> {
+    type Key = HoldemSubtreeKey<N>;
+
+    fn get_subtree_key(state: &Self::State) -> Option<Self::Key> {
+        let data = &state.game_data;
+
+        let candidates = if matches!(data.stage, HoldemStage::Deal) {
+            deal_candidates(state).0.len()
+        } else {
+            0
+        };
+
+        Some(HoldemSubtreeKey {
+            street: data.street,
+            stage: data.stage,
+            board_len: data.board_len,
+            candidates,
+            player_stacks: state.player_stacks,
+            player_committed: state.player_committed,
+            player_folded: state.player_folded,
+            active_player: state.active_player,
+            last_aggressor: state.last_aggressor,
+            pot: state.pot,
+            last_raise: state.last_raise,
+            current_bet: state.current_bet,
+            acted: data.acted,
+        })
+    }
+}
+
+/// Returns the cards that could be dealt as the highest card still to come this street, in
+/// descending order, along with the number of cards still to come.
+fn deal_candidates<const N: usize>(state: &HoldemState<N>) -> (Vec<Card>, usize) {
+    let data = &state.game_data;
+    let board = data.board();
+    let remaining = data.street.board_size() - board.len();
+
+    let bound = (board.len() > data.street.previous_board_size()).then(|| board[board.len() - 1]);
+
+    let mut candidates = data
+        .deck
+        .cards()
+        .iter()
+        .copied()
+        .filter(|&c| bound.is_none_or(|b| c < b))
+        .collect::<Vec<_>>();
+    candidates.sort_unstable_by(|a, b| b.cmp(a));
+
+    (candidates, remaining)
+}
+
+/// Maps the hole cards of the player to act to one of the 1326 possible combinations.
+pub struct HoldemParameterMapping<const N: usize>;
+
+impl<const N: usize> ParameterMapping for HoldemParameterMapping<N> {
+    type State = HoldemState<N>;
+
+    fn get_parameter_count(_state: &Self::State) -> usize {
+        ncr(52, 2)
+    }
+
+    fn get_parameter_index(state: &Self::State) -> usize {
+        if let HoldemStage::PlayerAction(player) = state.game_data.stage {
+            HoleCardRange::combo_index(state.game_data.hole_cards[player as usize])
+        } else {
+            panic!("no parameter index for a non-player action stage")
+        }
+    }
+
+    fn get_parameter_description(state: &Self::State, alternate_index: Option<usize>) -> String {
+        let index = alternate_index.unwrap_or_else(|| Self::get_parameter_index(state));
+        let cards = HoleCardRange::combo_from_index(index);
+        format!("{}{}", cards[0], cards[1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::mem::MaybeUninit;
+
+    use rand::SeedableRng;
+
+    use game_tree::{sample_game, Parameter, TreeEstimator};
+    use util::rng::JKiss32Rng;
+
+    use Card::*;
+
+    type G2 = HoldemGame<2>;
+    type G3 = HoldemGame<3>;
+
+    fn config<const N: usize>(stacks: [Value; N]) -> HoldemConfig<N> {
+        HoldemConfig {
+            stacks,
+            small_blind: 1,
+            big_blind: 2,
+            bet_sizing: BetSizing::default(),
+        }
+    }
+
+    fn actions<G: Game>(state: &G::State) -> Vec<G::Action> {
+        let mut events = Vec::new();
+        G::populate_events(state, &mut events);
+        events
+            .into_iter()
+            .map(|e| match e {
+                Event::Action(a) => a,
+                _ => panic!("not an action event"),
+            })
+            .collect()
+    }
+
+    fn act<const N: usize>(state: &mut HoldemState<N>, action: HoldemAction) {
+        assert!(
+            actions::<HoldemGame<N>>(state).contains(&action),
+            "{:?} is not available",
+            action
+        );
+        HoldemGame::<N>::advance_state(state, Event::Action(action));
+    }
+
+    fn deal<const N: usize>(state: &mut HoldemState<N>, cards: &[Card]) {
+        for &card in cards {
+            HoldemGame::<N>::advance_state(state, Event::Chance(card));
+        }
+    }
+
+    fn utilities<const N: usize>(state: &HoldemState<N>) -> [f32; N] {
+        let mut utilities = [0.0; N];
+        HoldemGame::<N>::get_terminal_utilities(state, &mut utilities);
+        utilities
+    }
+
+    #[test]
+    fn test_preflop_fold() {
+        let mut state = HoldemState::from_cards(&config([100, 100]), [[CA, DA], [C2, D7]]);
+
+        assert_eq!(state.game_data.stage, HoldemStage::PlayerAction(0));
+        assert_eq!(
+            actions::<G2>(&state),
+            [
+                HoldemAction::Fold,
+                HoldemAction::Call,
+                HoldemAction::Raise(6),
+                HoldemAction::AllIn,
+            ]
+        );
+
+        act(&mut state, HoldemAction::Fold);
+
+        assert_eq!(state.game_data.stage, HoldemStage::Terminal);
+        assert_eq!(utilities(&state), [-1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_memoized_estimate() {
+        #[allow(dead_code)]
+        struct Weight(f32);
+
+        impl Parameter for Weight {
+            fn initialize(parameters: &mut [MaybeUninit<Self>]) -> &mut [Self] {
+                for p in parameters.iter_mut() {
+                    p.write(Weight(0.0));
+                }
+                unsafe { &mut *(parameters as *mut [MaybeUninit<Self>] as *mut [Self]) }
+            }
+        }
+
+        let mut state = HoldemState::from_cards(&config([20, 20]), [[CA, DA], [C2, D7]]);
+        act(&mut state, HoldemAction::Call);
+        act(&mut state, HoldemAction::Check);
+        deal(&mut state, &[SK, H7, D2]);
+        act(&mut state, HoldemAction::Check);
+        act(&mut state, HoldemAction::Check);
+
+        let full = TreeEstimator::<G2, Weight>::from_root(state.clone());
+        let memoized = TreeEstimator::<G2, Weight>::from_root_memoized(state);
+
+        assert!(full.chance_nodes() > 0);
+        assert_eq!(memoized.statistics(), full.statistics());
+    }
+
+    #[test]
+    fn test_check_down() {
+        let mut state = HoldemState::from_cards(&config([100, 100]), [[CA, DA], [C2, D7]]);
+
+        act(&mut state, HoldemAction::Call);
+        assert_eq!(state.game_data.stage, HoldemStage::PlayerAction(1));
+        act(&mut state, HoldemAction::Check);
+
+        assert_eq!(state.game_data.stage, HoldemStage::Deal);
+        assert_eq!(state.pot, 4);
+
+        deal(&mut state, &[SK, H7, D2]);
+        assert_eq!(state.game_data.street, Street::Flop);
+        assert_eq!(state.game_data.stage, HoldemStage::PlayerAction(1));
+        assert_eq!(
+            actions::<G2>(&state),
+            [
+                HoldemAction::Check,
+                HoldemAction::Bet(4),
+                HoldemAction::AllIn
+            ]
+        );
+
+        act(&mut state, HoldemAction::Check);
+        act(&mut state, HoldemAction::Bet(4));
+        act(&mut state, HoldemAction::Call);
+
+        deal(&mut state, &[S3]);
+        act(&mut state, HoldemAction::Check);
+        act(&mut state, HoldemAction::Check);
+
+        deal(&mut state, &[H2]);
+        act(&mut state, HoldemAction::Check);
+        act(&mut state, HoldemAction::Check);
+
+        assert_eq!(state.game_data.stage, HoldemStage::Terminal);
+        assert_eq!(state.game_data.board(), [SK, H7, D2, S3, H2]);
+
+        // Twos and sevens beat aces.
+        assert_eq!(utilities(&state), [-6.0, 6.0]);
+    }
+
+    #[test]
+    fn test_side_pots() {
+        let mut state =
+            HoldemState::from_cards(&config([50, 100, 200]), [[CA, DA], [CK, DK], [CQ, DQ]]);
+
+        assert_eq!(state.game_data.stage, HoldemStage::PlayerAction(2));
+        act(&mut state, HoldemAction::AllIn);
+        act(&mut state, HoldemAction::Call);
+        act(&mut state, HoldemAction::Call);
+
+        // Everybody is all-in, so the board runs out.
+        assert_eq!(state.game_data.stage, HoldemStage::Deal);
+        deal(&mut state, &[S9, S2, H5, H3, D8]);
+
+        assert_eq!(state.game_data.stage, HoldemStage::Terminal);
+        assert_eq!(utilities(&state), [100.0, 0.0, -100.0]);
+    }
+
+    #[test]
+    fn test_chance_weights() {
+        let mut rng = JKiss32Rng::seed_from_u64(0);
+
+        let mut state = HoldemState::random(&config([100, 100]), &mut rng);
+        act(&mut state, HoldemAction::Call);
+        act(&mut state, HoldemAction::Check);
+
+        let mut events = Vec::new();
+
+        for _ in 0..5 {
+            while G2::get_stage(&state).is_chance() {
+                G2::populate_events(&state, &mut events);
+
+                let total = events
+                    .iter()
+                    .map(|&e| match e {
+                        Event::Chance(c) => G2::get_chance_weight(&state, c),
+                        _ => panic!("not a chance event"),
+                    })
+                    .sum::<f32>();
+                assert!(
+                    (total - 1.0).abs() < 1e-4,
+                    "chance weights sum to {}",
+                    total
+                );
+
+                let (card, index) = G2::sample_chance(&state, &mut rng);
+                assert!(matches!(events[index], Event::Chance(c) if c == card));
+
+                G2::advance_state(&mut state, Event::Chance(card));
+            }
+
+            if G2::get_stage(&state).is_terminal() {
+                break;
+            }
+
+            act(&mut state, HoldemAction::Check);
+            act(&mut state, HoldemAction::Check);
+        }
+
+        assert_eq!(G2::get_stage(&state), HoldemStage::Terminal);
+
+        let board = state.game_data.board();
+        assert!(board[0] > board[1] && board[1] > board[2]);
+    }
+
+    #[test]
+    fn test_conformance() {
+        let mut rng = JKiss32Rng::seed_from_u64(0);
+
+        // Boards are dealt from what's left of the deck, so only states with the same hole cards
+        // share public histories.
+        let config = config([100, 40, 100]);
+        let violations = sample_game::<G3, _, _, _, _, 3>(
+            |_: &mut JKiss32Rng| HoldemState::from_cards(&config, [[CA, DA], [CK, DK], [C2, D7]]),
+            200,
+            &mut rng,
+            |state: &HoldemState<3>, player| state.game_data.hole_cards[player],
+        );
+
+        assert!(violations.is_empty(), "{}", violations[0]);
+    }
+
+    #[test]
+    fn test_short_all_in() {
+        let mut state =
+            HoldemState::from_cards(&config([100, 10, 100]), [[CA, DA], [CK, DK], [CQ, DQ]]);
+
+        act(&mut state, HoldemAction::Call);
+        act(&mut state, HoldemAction::Raise(8));
+        act(&mut state, HoldemAction::AllIn);
+
+        // The big blind's all-in is not a full raise, so the minimum raise is unchanged.
+        assert_eq!(state.current_bet, 10);
+        assert_eq!(state.min_raise_to(), 16);
+        assert_eq!(state.game_data.stage, HoldemStage::PlayerAction(2));
+
+        act(&mut state, HoldemAction::Call);
+
+        // Nor does it reopen the betting to the raiser, who can only call or fold.
+        assert_eq!(state.game_data.stage, HoldemStage::PlayerAction(0));
+        assert_eq!(
+            actions::<G3>(&state),
+            [HoldemAction::Fold, HoldemAction::Call]
+        );
+
+        act(&mut state, HoldemAction::Call);
+
+        assert_eq!(G3::get_stage(&state), HoldemStage::Deal);
+        assert_eq!(state.pot, 30);
+    }
+
+    #[test]
+    fn test_bet_sizing() {
+        use crate::betting::{BetSize, StreetSizing};
+
+        let preflop = StreetSizing {
+            bets: vec![],
+            raises: vec![BetSize::Fixed(5), BetSize::AllIn],
+            all_in_threshold: None,
+            merge_threshold: None,
+        };
+        let postflop = StreetSizing {
+            bets: vec![BetSize::PotFraction(0.5), BetSize::PotFraction(0.75)],
+            raises: vec![],
+            all_in_threshold: Some(0.5),
+            merge_threshold: None,
+        };
+
+        let mut config = config([100, 100]);
+        config.bet_sizing = BetSizing::new(vec![preflop, postflop]);
+
+        let mut state = HoldemState::from_cards(&config, [[CA, DA], [C2, D7]]);
+        assert_eq!(
+            actions::<G2>(&state),
+            [
+                HoldemAction::Fold,
+                HoldemAction::Call,
+                HoldemAction::Raise(5),
+                HoldemAction::AllIn,
+            ]
+        );
+
+        act(&mut state, HoldemAction::Raise(5));
+        // The fixed size is below a minimum raise.
+        assert_eq!(
+            actions::<G2>(&state),
+            [
+                HoldemAction::Fold,
+                HoldemAction::Call,
+                HoldemAction::Raise(8),
+                HoldemAction::AllIn,
+            ]
+        );
+        act(&mut state, HoldemAction::Call);
+
+        deal(&mut state, &[SK, H7, D2]);
+        assert_eq!(
+            actions::<G2>(&state),
+            [
+                HoldemAction::Check,
+                HoldemAction::Bet(5),
+                HoldemAction::Bet(8)
+            ]
+        );
+        act(&mut state, HoldemAction::Bet(8));
+
+        // Later streets use the last configured round, and nothing is offered facing a bet.
+        assert_eq!(
+            actions::<G2>(&state),
+            [HoldemAction::Fold, HoldemAction::Call]
+        );
+    }
+
+    #[test]
+    fn test_parameter_mapping() {
+        let state = HoldemState::from_cards(&config([100, 100]), [[D7, SK], [C2, H2]]);
+
+        let index = HoldemParameterMapping::get_parameter_index(&state);
+        assert!(index < HoldemParameterMapping::get_parameter_count(&state));
+        assert_eq!(
+            HoldemParameterMapping::get_parameter_description(&state, None),
+            "Ks7d"
+        );
+    }
+}
@@ -0,0 +1,472 @@
+use std::convert::TryFrom;
+
+use util::math::ncr;
+
+use crate::card::Card;
+use crate::rank::Rank;
+use crate::suit::Suit;
+
+const RANKS: usize = 13;
+const SUITS: usize = 4;
+const MAX_ROUNDS: usize = 4;
+
+/// Assigns a dense index to every class of suit-isomorphic hands, where a hand is a sequence of
+/// rounds of cards (e.g. hole cards, flop, turn, river) and the order of cards within a round
+/// doesn't matter.
+///
+/// Two hands are isomorphic when one can be turned into the other by permuting suits.  A suit's
+/// part of a hand is the sequence of rank sets it has in each round, so a class is identified by
+/// the multiset of those four sequences.  Classes are grouped into configurations by how many
+/// cards each suit has per round, and indexed within their configuration by combining the index
+/// of each suit's sequence.
+///
+/// An indexer over a single round of two cards gives the 169 preflop classes, which can stand in
+/// for the 1326 raw combos as a `ParameterMapping`'s parameter indices.
+pub struct HandIndexer {
+    cards_per_round: Vec<usize>,
+    /// For each round, every configuration, in index order.
+    configurations: Vec<Vec<Configuration>>,
+}
+
+struct Configuration {
+    /// The number of cards each suit has in each round, sorted in descending order.
+    sizes: [[u8; MAX_ROUNDS]; SUITS],
+    /// The index of the first hand with this configuration.
+    offset: usize,
+    size: usize,
+}
+
+impl HandIndexer {
+    /// Panics if there are no rounds, more than four rounds, or more than 52 cards.
+    pub fn new(cards_per_round: &[usize]) -> Self {
+        assert!(
+            (1..=MAX_ROUNDS).contains(&cards_per_round.len()),
+            "must have between one and four rounds"
+        );
+        assert!(
+            cards_per_round.iter().sum::<usize>() <= RANKS * SUITS,
+            "too many cards"
+        );
+
+        let configurations = (0..cards_per_round.len())
+            .map(|round| enumerate_configurations(&cards_per_round[..=round]))
+            .collect();
+
+        Self {
+            cards_per_round: cards_per_round.to_vec(),
+            configurations,
+        }
+    }
+
+    /// Hole cards, flop, turn and river.
+    pub fn holdem() -> Self {
+        Self::new(&[2, 3, 1, 1])
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.cards_per_round.len()
+    }
+
+    /// The number of isomorphism classes of hands dealt up to and including `round`.
+    pub fn size(&self, round: usize) -> usize {
+        let last = self.configurations[round].last().unwrap();
+        last.offset + last.size
+    }
+
+    /// Returns the index of the hand made of all rounds up to the last round `cards` covers.
+    /// Panics if `cards` doesn't end on a round boundary, or contains duplicates.
+    pub fn index(&self, cards: &[Card]) -> usize {
+        let round = self.round_of(cards.len());
+
+        let mut sets = [[0u16; MAX_ROUNDS]; SUITS];
+        let mut start = 0;
+        for (r, &n) in self.cards_per_round[..=round].iter().enumerate() {
+            for &card in &cards[start..start + n] {
+                let (suit, rank) = suit_and_rank(card);
+                let bit = 1 << rank;
+                assert!(
+                    sets[suit].iter().all(|&s| s & bit == 0),
+                    "duplicate card in hand"
+                );
+                sets[suit][r] |= bit;
+            }
+            start += n;
+        }
+
+        let mut suits = [(Sizes::default(), 0usize); SUITS];
+        for (s, suit) in suits.iter_mut().enumerate() {
+            for (size, set) in suit.0.iter_mut().zip(&sets[s][..=round]) {
+                *size = set.count_ones() as u8;
+            }
+            suit.1 = sequence_index(&sets[s][..=round]);
+        }
+        suits.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut sizes = [Sizes::default(); SUITS];
+        for (s, suit) in sizes.iter_mut().zip(suits.iter()) {
+            *s = suit.0;
+        }
+
+        let configurations = &self.configurations[round];
+        let configuration = configurations
+            .binary_search_by(|c| sizes.cmp(&c.sizes))
+            .map(|i| &configurations[i])
+            .expect("no configuration for hand");
+
+        let mut index = 0;
+        for group in groups(&configuration.sizes) {
+            let group_size = group_size(&configuration.sizes[group.start], round, group.len());
+            let group_index = multiset_index(suits[group.clone()].iter().map(|s| s.1));
+            index = index * group_size + group_index;
+        }
+
+        configuration.offset + index
+    }
+
+    /// Returns a canonical hand for the class at `index` in `round`, with the cards of each round
+    /// in order.
+    pub fn unindex(&self, round: usize, index: usize) -> Vec<Card> {
+        assert!(index < self.size(round), "hand index out of range");
+
+        let configurations = &self.configurations[round];
+        let configuration =
+            &configurations[configurations.partition_point(|c| c.offset <= index) - 1];
+
+        let groups = groups(&configuration.sizes).collect::<Vec<_>>();
+
+        let mut sequences = [0usize; SUITS];
+        let mut remainder = index - configuration.offset;
+        for group in groups.iter().rev() {
+            let group_size = group_size(&configuration.sizes[group.start], round, group.len());
+            let group_index = remainder % group_size;
+            remainder /= group_size;
+            multiset_unindex(group_index, &mut sequences[group.clone()]);
+        }
+
+        let mut cards = vec![Vec::new(); round + 1];
+        for (s, &sequence) in sequences.iter().enumerate() {
+            let sets = sequence_unindex(sequence, &configuration.sizes[s][..=round]);
+            let suit = Suit::try_from(((s + 1) << 4) as u8).unwrap();
+            for (r, &set) in sets.iter().enumerate() {
+                for rank in (0..RANKS).filter(|&i| set & 1 << i != 0) {
+                    let rank = Rank::try_from(rank as u8 + 2).unwrap();
+                    cards[r].push(Card::from_rank_and_suit(rank, suit));
+                }
+            }
+        }
+
+        cards.into_iter().flatten().collect()
+    }
+
+    fn round_of(&self, card_count: usize) -> usize {
+        let mut total = 0;
+        for (round, &n) in self.cards_per_round.iter().enumerate() {
+            total += n;
+            if total == card_count {
+                return round;
+            }
+        }
+        panic!("card count doesn't end on a round boundary");
+    }
+}
+
+type Sizes = [u8; MAX_ROUNDS];
+
+fn suit_and_rank(card: Card) -> (usize, usize) {
+    ((card.suit() as usize >> 4) - 1, card.rank() as usize - 2)
+}
+
+/// Ranges of suits that share the same sizes in a sorted configuration.
+fn groups(sizes: &[Sizes; SUITS]) -> impl Iterator<Item = std::ops::Range<usize>> + '_ {
+    let mut start = 0;
+    std::iter::from_fn(move || {
+        (start < SUITS).then(|| {
+            let end = (start..SUITS)
+                .find(|&i| sizes[i] != sizes[start])
+                .unwrap_or(SUITS);
+            let group = start..end;
+            start = end;
+            group
+        })
+    })
+}
+
+/// The number of distinct rank set sequences a suit with `sizes` can have.
+fn sequence_count(sizes: &[u8]) -> usize {
+    let mut used = 0;
+    sizes
+        .iter()
+        .map(|&n| {
+            let count = ncr(RANKS - used, n as usize);
+            used += n as usize;
+            count
+        })
+        .product()
+}
+
+/// The number of ways to fill a group of `suits` suits that all share `sizes`.
+fn group_size(sizes: &Sizes, round: usize, suits: usize) -> usize {
+    let sequences = sequence_count(&sizes[..=round]);
+    ncr(sequences + suits - 1, suits)
+}
+
+/// Indexes a suit's rank sets for each round.  The ranks of each round are indexed among the
+/// ranks not used in earlier rounds.
+fn sequence_index(sets: &[u16]) -> usize {
+    let mut index = 0;
+    let mut used = 0u16;
+
+    for &set in sets {
+        let available = RANKS - used.count_ones() as usize;
+        let n = set.count_ones() as usize;
+
+        let mut set_index = 0;
+        for (j, rank) in (0..RANKS).filter(|&i| set & 1 << i != 0).enumerate() {
+            let position = rank - (used & ((1 << rank) - 1)).count_ones() as usize;
+            set_index += ncr(position, j + 1);
+        }
+
+        index = index * ncr(available, n) + set_index;
+        used |= set;
+    }
+
+    index
+}
+
+fn sequence_unindex(mut index: usize, sizes: &[u8]) -> Vec<u16> {
+    let mut available = Vec::with_capacity(sizes.len());
+    let mut used = 0;
+    for &n in sizes {
+        available.push(RANKS - used);
+        used += n as usize;
+    }
+
+    let mut set_indices = vec![0; sizes.len()];
+    for r in (0..sizes.len()).rev() {
+        let count = ncr(available[r], sizes[r] as usize);
+        set_indices[r] = index % count;
+        index /= count;
+    }
+
+    let mut sets = Vec::with_capacity(sizes.len());
+    let mut used = 0u16;
+
+    for (&n, &set_index) in sizes.iter().zip(set_indices.iter()) {
+        let mut remainder = set_index;
+        let mut set = 0u16;
+
+        for j in (1..=n as usize).rev() {
+            let position = (0..RANKS).rev().find(|&p| ncr(p, j) <= remainder).unwrap();
+            remainder -= ncr(position, j);
+
+            // Map the position among the unused ranks back to a rank.
+            let rank = (0..RANKS)
+                .filter(|&i| used & 1 << i == 0)
+                .nth(position)
+                .unwrap();
+            set |= 1 << rank;
+        }
+
+        used |= set;
+        sets.push(set);
+    }
+
+    sets
+}
+
+/// Indexes a multiset of sequence indices, given in descending order.
+fn multiset_index(values: impl Iterator<Item = usize>) -> usize {
+    let values = values.collect::<Vec<_>>();
+    let k = values.len();
+
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| ncr(x + k - 1 - i, k - i))
+        .sum()
+}
+
+/// The inverse of `multiset_index`.  Fills `values` in descending order.
+fn multiset_unindex(mut index: usize, values: &mut [usize]) {
+    let k = values.len();
+
+    for (i, value) in values.iter_mut().enumerate() {
+        let choose = k - i;
+        let offset = k - 1 - i;
+        let mut y = offset;
+        while ncr(y + 1, choose) <= index {
+            y += 1;
+        }
+        index -= ncr(y, choose);
+        *value = y - offset;
+    }
+}
+
+/// Finds every way of splitting each round's cards among the four suits, up to reordering the
+/// suits, and assigns each an offset into the index space.
+fn enumerate_configurations(cards_per_round: &[usize]) -> Vec<Configuration> {
+    fn distribute(
+        cards_per_round: &[usize],
+        round: usize,
+        suit: usize,
+        remaining: usize,
+        sizes: &mut [Sizes; SUITS],
+        found: &mut Vec<[Sizes; SUITS]>,
+    ) {
+        if round == cards_per_round.len() {
+            let mut sorted = *sizes;
+            sorted.sort_unstable_by(|a, b| b.cmp(a));
+            found.push(sorted);
+            return;
+        }
+
+        if suit == SUITS - 1 {
+            let used = sizes[suit].iter().map(|&n| n as usize).sum::<usize>();
+            if used + remaining <= RANKS {
+                sizes[suit][round] = remaining as u8;
+                let next = cards_per_round.get(round + 1).copied().unwrap_or(0);
+                distribute(cards_per_round, round + 1, 0, next, sizes, found);
+                sizes[suit][round] = 0;
+            }
+            return;
+        }
+
+        let used = sizes[suit].iter().map(|&n| n as usize).sum::<usize>();
+        for n in 0..=remaining.min(RANKS - used) {
+            sizes[suit][round] = n as u8;
+            distribute(
+                cards_per_round,
+                round,
+                suit + 1,
+                remaining - n,
+                sizes,
+                found,
+            );
+        }
+        sizes[suit][round] = 0;
+    }
+
+    let mut found = Vec::new();
+    distribute(
+        cards_per_round,
+        0,
+        0,
+        cards_per_round[0],
+        &mut [Sizes::default(); SUITS],
+        &mut found,
+    );
+
+    found.sort_unstable_by(|a, b| b.cmp(a));
+    found.dedup();
+
+    let round = cards_per_round.len() - 1;
+    let mut offset = 0;
+
+    found
+        .into_iter()
+        .map(|sizes| {
+            let size = groups(&sizes)
+                .map(|g| group_size(&sizes[g.start], round, g.len()))
+                .product();
+
+            let configuration = Configuration {
+                sizes,
+                offset,
+                size,
+            };
+            offset += size;
+            configuration
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::seq::index::sample;
+    use rand::SeedableRng;
+
+    use util::rng::JKiss32Rng;
+
+    use crate::card::card_from_index;
+
+    #[test]
+    fn test_holdem_sizes() {
+        let indexer = HandIndexer::holdem();
+
+        assert_eq!(indexer.size(0), 169);
+        assert_eq!(indexer.size(1), 1286792);
+        assert_eq!(indexer.size(2), 55190538);
+        assert_eq!(indexer.size(3), 2428287420);
+
+        // Without distinguishing which street each board card came on.
+        assert_eq!(HandIndexer::new(&[2, 4]).size(1), 13960050);
+        assert_eq!(HandIndexer::new(&[2, 5]).size(1), 123156254);
+    }
+
+    #[test]
+    fn test_preflop_classes() {
+        let indexer = HandIndexer::holdem();
+
+        let mut counts = vec![0; 169];
+        for a in 0..52 {
+            for b in 0..a {
+                counts[indexer.index(&[card_from_index(a), card_from_index(b)])] += 1;
+            }
+        }
+
+        // Pairs and offsuit hands have 6 and 12 combos, suited hands have 4.
+        assert_eq!(counts.iter().filter(|&&c| c == 6).count(), 13);
+        assert_eq!(counts.iter().filter(|&&c| c == 4).count(), 78);
+        assert_eq!(counts.iter().filter(|&&c| c == 12).count(), 78);
+    }
+
+    #[test]
+    fn test_isomorphic_hands() {
+        use Card::*;
+
+        let indexer = HandIndexer::holdem();
+
+        // Swap hearts and spades, and reorder cards within rounds.
+        let a = indexer.index(&[SA, HK, S2, H7, D9, C4]);
+        let b = indexer.index(&[SK, HA, S7, D9, H2, C4]);
+        assert_eq!(a, b);
+
+        // Same ranks, but the flop suits relate differently to the hole cards.
+        let c = indexer.index(&[SA, HK, H2, S7, D9, C4]);
+        assert_ne!(a, c);
+
+        // Which round a card comes in matters.
+        let d = indexer.index(&[SA, HK, S2, H7, C4, D9]);
+        assert_ne!(a, d);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let indexer = HandIndexer::holdem();
+        let mut rng = JKiss32Rng::seed_from_u64(0);
+
+        for round in 0..4 {
+            let card_count = [2, 5, 6, 7][round];
+
+            for _ in 0..2000 {
+                let cards = sample(&mut rng, 52, card_count)
+                    .into_iter()
+                    .map(|i| card_from_index(i as u8))
+                    .collect::<Vec<_>>();
+
+                let index = indexer.index(&cards);
+                assert!(index < indexer.size(round));
+
+                let canonical = indexer.unindex(round, index);
+                assert_eq!(canonical.len(), card_count);
+                assert_eq!(indexer.index(&canonical), index);
+            }
+
+            for index in (0..indexer.size(round)).step_by(indexer.size(round) / 1000 + 1) {
+                assert_eq!(indexer.index(&indexer.unindex(round, index)), index);
+            }
+        }
+    }
+}
@@ -1,13 +1,28 @@
+mod betting;
 mod card;
 mod deck;
+mod evaluator;
 mod hand;
+mod holdem;
+mod isomorphism;
+mod parse;
+mod range;
 mod rank;
 mod state;
 mod suit;
 
-pub use self::card::{Card, CardRange};
+pub use self::betting::{BetSize, BetSizing, StreetSizing};
+pub use self::card::{parse_cards, Card, CardRange};
 pub use self::deck::{full_deck, Deck};
+pub use self::evaluator::{HandClass, HandEvaluator, HandRank};
 pub use self::hand::{Hand, HandComparator};
+pub use self::holdem::{
+    HoldemAction, HoldemConfig, HoldemData, HoldemGame, HoldemParameterMapping, HoldemStage,
+    HoldemState, HoldemSubtreeKey, Street,
+};
+pub use self::isomorphism::HandIndexer;
+pub use self::parse::ParseError;
+pub use self::range::HoleCardRange;
 pub use self::rank::Rank;
 pub use self::state::{Player, State, Value};
 pub use self::suit::Suit;
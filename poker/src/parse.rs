@@ -0,0 +1,39 @@
+use std::fmt;
+
+/// An error in a card or range specification, pointing to the token that caused it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    /// The offending token.
+    pub token: String,
+    /// Byte offset of the token within the parsed string.
+    pub position: usize,
+    pub reason: &'static str,
+}
+
+impl ParseError {
+    pub(crate) fn new(token: &str, position: usize, reason: &'static str) -> Self {
+        Self {
+            token: token.to_string(),
+            position,
+            reason,
+        }
+    }
+
+    /// Moves the error's position by `offset`, for when the token was parsed out of a larger string.
+    pub(crate) fn offset(mut self, offset: usize) -> Self {
+        self.position += offset;
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at position {}: \"{}\"",
+            self.reason, self.position, self.token
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
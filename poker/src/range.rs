@@ -0,0 +1,396 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use util::math::ncr;
+
+use crate::card::{card_from_index, Card, CardRange};
+use crate::parse::ParseError;
+use crate::rank::Rank;
+use crate::suit::Suit;
+
+const COMBOS: usize = ncr(52, 2);
+
+const SUITS: [Suit; 4] = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
+
+/// A weighted set of two-card starting hands.
+///
+/// Ranges are parsed from comma-separated lists of hands, each optionally followed by a weight,
+/// e.g. "AKs, 77+, T9o-76o, AsKd:0.5".  Supported forms are specific combos ("AsKd"), hand
+/// classes ("AK", "AKs", "AKo", "77"), everything above a class ("77+", "A2s+"), and spans
+/// between two classes ("77-44", "A5s-A2s", "T9o-76o").  Hands without a weight get a weight
+/// of 1.  Later hands overwrite the weights of earlier ones.
+#[derive(Clone, PartialEq)]
+pub struct HoleCardRange {
+    weights: Vec<f32>,
+}
+
+impl HoleCardRange {
+    pub fn empty() -> Self {
+        Self {
+            weights: vec![0.0; COMBOS],
+        }
+    }
+
+    pub fn full() -> Self {
+        Self {
+            weights: vec![1.0; COMBOS],
+        }
+    }
+
+    /// Maps two distinct cards, in any order, to one of the 1326 possible combinations.
+    pub fn combo_index(cards: [Card; 2]) -> usize {
+        let range = CardRange::from_parts(Card::C2, Card::SA, 0xFFFFFFFFFFFFFFFF >> 12);
+        let (a, b) = (cards[0].max(cards[1]), cards[0].min(cards[1]));
+        range.combo_index_of(&[a, b]).unwrap()
+    }
+
+    /// The inverse of `combo_index`.  The higher card comes first.
+    pub fn combo_from_index(index: usize) -> [Card; 2] {
+        assert!(index < COMBOS, "combo index out of range");
+        let high = (1..52).rev().find(|&x| ncr(x, 2) <= index).unwrap();
+        let low = index - ncr(high, 2);
+        [card_from_index(high as u8), card_from_index(low as u8)]
+    }
+
+    pub fn weight(&self, cards: [Card; 2]) -> f32 {
+        self.weights[Self::combo_index(cards)]
+    }
+
+    pub fn set_weight(&mut self, cards: [Card; 2], weight: f32) {
+        self.weights[Self::combo_index(cards)] = weight;
+    }
+
+    /// The weight of every combo, indexed by `combo_index`.
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+
+    /// The number of combos with a non-zero weight.
+    pub fn len(&self) -> usize {
+        self.weights.iter().filter(|&&w| w > 0.0).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over combos with a non-zero weight, with their weights.
+    pub fn combos(&self) -> impl Iterator<Item = ([Card; 2], f32)> + '_ {
+        self.weights
+            .iter()
+            .enumerate()
+            .filter(|(_, &w)| w > 0.0)
+            .map(|(i, &w)| (Self::combo_from_index(i), w))
+    }
+}
+
+impl fmt::Debug for HoleCardRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HoleCardRange({})", self)
+    }
+}
+
+/// Writes hand classes where every combo in the class shares a weight, and specific combos
+/// otherwise.  The output parses back into the same range.
+impl fmt::Display for HoleCardRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        let mut write_hand = |f: &mut fmt::Formatter<'_>, hand: &str, weight: f32| {
+            let separator = if first { "" } else { ", " };
+            first = false;
+            if weight == 1.0 {
+                write!(f, "{}{}", separator, hand)
+            } else {
+                write!(f, "{}{}:{}", separator, hand, weight)
+            }
+        };
+
+        for high in (2..=14).rev().map(rank) {
+            for low in (2..=high as u8).rev().map(rank) {
+                let kinds: &[Kind] = if high == low {
+                    &[Kind::Pair]
+                } else {
+                    &[Kind::Suited, Kind::Offsuit]
+                };
+
+                for &kind in kinds {
+                    let combos = class_combos(high, low, kind);
+                    let weight = self.weight(combos[0]);
+
+                    if combos.iter().all(|&c| self.weight(c) == weight) {
+                        if weight > 0.0 {
+                            write_hand(f, &class_name(high, low, kind), weight)?;
+                        }
+                    } else {
+                        for &c in combos.iter().filter(|&&c| self.weight(c) > 0.0) {
+                            write_hand(f, &format!("{}{}", c[0], c[1]), self.weight(c))?;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for HoleCardRange {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut range = Self::empty();
+
+        let mut position = 0;
+        for part in s.split(',') {
+            let token = part.trim();
+            let offset = position + (part.len() - part.trim_start().len());
+            position += part.len() + 1;
+
+            if token.is_empty() {
+                if s.trim().is_empty() {
+                    break;
+                }
+                return Err(ParseError::new(token, offset, "empty hand"));
+            }
+
+            let (hands, weight) = match token.split_once(':') {
+                Some((hands, weight)) => {
+                    let weight = weight
+                        .trim()
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|w| w.is_finite() && *w >= 0.0)
+                        .ok_or_else(|| ParseError::new(token, offset, "invalid weight"))?;
+                    (hands.trim_end(), weight)
+                }
+                None => (token, 1.0),
+            };
+
+            for combo in parse_hands(hands).map_err(|r| ParseError::new(token, offset, r))? {
+                range.set_weight(combo, weight);
+            }
+        }
+
+        Ok(range)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Pair,
+    Suited,
+    Offsuit,
+    Any,
+}
+
+fn rank(value: u8) -> Rank {
+    Rank::try_from(value).unwrap()
+}
+
+fn class_name(high: Rank, low: Rank, kind: Kind) -> String {
+    let suffix = match kind {
+        Kind::Suited => "s",
+        Kind::Offsuit => "o",
+        Kind::Pair | Kind::Any => "",
+    };
+    format!("{}{}{}", high, low, suffix)
+}
+
+fn class_combos(high: Rank, low: Rank, kind: Kind) -> Vec<[Card; 2]> {
+    let mut combos = Vec::with_capacity(16);
+
+    for (i, &a) in SUITS.iter().enumerate() {
+        for (j, &b) in SUITS.iter().enumerate() {
+            let include = match kind {
+                Kind::Pair => i < j,
+                Kind::Suited => i == j,
+                Kind::Offsuit => i != j,
+                Kind::Any => true,
+            };
+
+            if include {
+                combos.push([
+                    Card::from_rank_and_suit(high, a),
+                    Card::from_rank_and_suit(low, b),
+                ]);
+            }
+        }
+    }
+
+    combos
+}
+
+/// Parses a hand class such as "AK", "AKs", "AKo" or "77".
+fn parse_class(s: &str) -> Result<(Rank, Rank, Kind), &'static str> {
+    if !s.is_ascii() || !(2..=3).contains(&s.len()) {
+        return Err("invalid hand");
+    }
+
+    let high = s[0..1].parse::<Rank>().map_err(|_| "invalid card rank")?;
+    let low = s[1..2].parse::<Rank>().map_err(|_| "invalid card rank")?;
+
+    let kind = match &s[2..] {
+        "" if high == low => Kind::Pair,
+        "" => Kind::Any,
+        "s" | "o" if high == low => return Err("pairs can't be suited or offsuit"),
+        "s" => Kind::Suited,
+        "o" => Kind::Offsuit,
+        _ => return Err("invalid hand suffix"),
+    };
+
+    if high < low {
+        return Err("ranks must be in descending order");
+    }
+
+    Ok((high, low, kind))
+}
+
+/// Parses a single hand specification, without a weight.
+fn parse_hands(s: &str) -> Result<Vec<[Card; 2]>, &'static str> {
+    // A specific combo.
+    if s.len() == 4 && s.is_ascii() {
+        if let (Ok(a), Ok(b)) = (s[..2].parse::<Card>(), s[2..].parse::<Card>()) {
+            if a == b {
+                return Err("duplicate card");
+            }
+            return Ok(vec![[a, b]]);
+        }
+    }
+
+    let classes = if let Some(class) = s.strip_suffix('+') {
+        let (high, low, kind) = parse_class(class)?;
+        if kind == Kind::Pair {
+            (low as u8..=14).map(|r| (rank(r), rank(r), kind)).collect()
+        } else {
+            (low as u8..high as u8)
+                .map(|r| (high, rank(r), kind))
+                .collect()
+        }
+    } else if let Some((from, to)) = s.split_once('-') {
+        let (a_high, a_low, a_kind) = parse_class(from.trim())?;
+        let (b_high, b_low, b_kind) = parse_class(to.trim())?;
+
+        if a_kind != b_kind {
+            return Err("both ends of a span must be the same kind of hand");
+        }
+
+        let (top, bottom) = if a_low >= b_low {
+            ((a_high, a_low), (b_high, b_low))
+        } else {
+            ((b_high, b_low), (a_high, a_low))
+        };
+
+        if a_kind == Kind::Pair {
+            (bottom.1 as u8..=top.1 as u8)
+                .map(|r| (rank(r), rank(r), a_kind))
+                .collect()
+        } else if top.0 == bottom.0 {
+            (bottom.1 as u8..=top.1 as u8)
+                .map(|r| (top.0, rank(r), a_kind))
+                .collect()
+        } else if top.0 as u8 - top.1 as u8 == bottom.0 as u8 - bottom.1 as u8 {
+            let gap = top.0 as u8 - top.1 as u8;
+            (bottom.1 as u8..=top.1 as u8)
+                .map(|r| (rank(r + gap), rank(r), a_kind))
+                .collect()
+        } else {
+            return Err("span must keep either the first rank or the gap between ranks");
+        }
+    } else {
+        vec![parse_class(s)?]
+    };
+
+    Ok(classes
+        .into_iter()
+        .flat_map(|(high, low, kind)| class_combos(high, low, kind))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use Card::*;
+
+    fn count(s: &str) -> usize {
+        s.parse::<HoleCardRange>().unwrap().len()
+    }
+
+    #[test]
+    fn test_combo_index() {
+        let mut seen = vec![false; COMBOS];
+
+        for a in 0..52 {
+            for b in 0..a {
+                let cards = [card_from_index(a), card_from_index(b)];
+                let index = HoleCardRange::combo_index([cards[1], cards[0]]);
+
+                assert!(!seen[index], "duplicate index");
+                seen[index] = true;
+
+                assert_eq!(HoleCardRange::combo_from_index(index), cards);
+            }
+        }
+
+        assert!(seen.into_iter().all(|s| s));
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(count("AsKd"), 1);
+        assert_eq!(count("AKs"), 4);
+        assert_eq!(count("AKo"), 12);
+        assert_eq!(count("AK"), 16);
+        assert_eq!(count("77"), 6);
+        assert_eq!(count("77+"), 48);
+        assert_eq!(count("77-55"), 18);
+        assert_eq!(count("A2s+"), 48);
+        assert_eq!(count("KTo+"), 36);
+        assert_eq!(count("A5s-A2s"), 16);
+        assert_eq!(count("T9o-76o"), 48);
+        assert_eq!(count("AKs, 77+, T9o-76o"), 100);
+        assert_eq!(count("AKs,AKs"), 4);
+        assert_eq!(count(""), 0);
+
+        let range = "AK:0.25, AsKs".parse::<HoleCardRange>().unwrap();
+        assert_eq!(range.weight([SA, SK]), 1.0);
+        assert_eq!(range.weight([HK, DA]), 0.25);
+        assert_eq!(range.weight([HK, DQ]), 0.0);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = "AKs, 77+, T9x".parse::<HoleCardRange>().unwrap_err();
+        assert_eq!(
+            (error.token.as_str(), error.position, error.reason),
+            ("T9x", 10, "invalid hand suffix")
+        );
+
+        let error = "AKs,  KQs:x".parse::<HoleCardRange>().unwrap_err();
+        assert_eq!(
+            (error.token.as_str(), error.position, error.reason),
+            ("KQs:x", 6, "invalid weight")
+        );
+
+        let error = "AKs,,KQs".parse::<HoleCardRange>().unwrap_err();
+        assert_eq!((error.position, error.reason), (4, "empty hand"));
+
+        assert!("KAs".parse::<HoleCardRange>().is_err());
+        assert!("77s".parse::<HoleCardRange>().is_err());
+        assert!("AsAs".parse::<HoleCardRange>().is_err());
+        assert!("T9o-75o".parse::<HoleCardRange>().is_err());
+        assert!("T9o-76s".parse::<HoleCardRange>().is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        let range = "AA, AKs, AsKd:0.5, T9o-76o:0.75"
+            .parse::<HoleCardRange>()
+            .unwrap();
+        let string = range.to_string();
+
+        assert!(string.starts_with("AA, AKs, AsKd:0.5, T9o:0.75"));
+        assert_eq!(string.parse::<HoleCardRange>().unwrap(), range);
+    }
+}
@@ -1,6 +1,9 @@
 use std::convert::{TryFrom, TryInto};
 use std::fmt::{self, Write};
 use std::mem;
+use std::str::FromStr;
+
+use crate::parse::ParseError;
 
 #[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[repr(u8)]
@@ -50,12 +53,41 @@ impl fmt::Debug for Rank {
     }
 }
 
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl FromStr for Rank {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "2" => Ok(Rank::Two),
+            "3" => Ok(Rank::Three),
+            "4" => Ok(Rank::Four),
+            "5" => Ok(Rank::Five),
+            "6" => Ok(Rank::Six),
+            "7" => Ok(Rank::Seven),
+            "8" => Ok(Rank::Eight),
+            "9" => Ok(Rank::Nine),
+            "T" | "t" => Ok(Rank::Ten),
+            "J" | "j" => Ok(Rank::Jack),
+            "Q" | "q" => Ok(Rank::Queen),
+            "K" | "k" => Ok(Rank::King),
+            "A" | "a" => Ok(Rank::Ace),
+            _ => Err(ParseError::new(s, 0, "invalid card rank")),
+        }
+    }
+}
+
 impl TryFrom<u8> for Rank {
     type Error = &'static str;
 
     fn try_from(value: u8) -> Result<Self, Self::Error> {
         if (2..=14).contains(&value) {
-            Ok(unsafe { mem::transmute(value) })
+            Ok(unsafe { mem::transmute::<u8, Rank>(value) })
         } else {
             Err("invalid card rank")
         }
@@ -1,8 +1,14 @@
 pub type Value = u32;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct Player(pub(crate) u8);
 
+impl Player {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct State<T, const N: usize> {
     pub player_stacks: [Value; N],
@@ -24,8 +30,7 @@ impl<T, const N: usize> State<T, N> {
     }
 
     pub fn next_active_player(&self) -> Option<Player> {
-        std::iter::repeat(0..N)
-            .take(2)
+        std::iter::repeat_n(0..N, 2)
             .flatten()
             .skip(self.active_player.0 as usize + 1)
             .find(|&p| !self.player_folded[p])
@@ -35,4 +40,86 @@ impl<T, const N: usize> State<T, N> {
     pub fn current_pot(&self) -> Value {
         self.pot + self.player_committed.iter().sum::<Value>()
     }
+
+    pub fn is_all_in(&self, player: Player) -> bool {
+        !self.player_folded[player.index()] && self.player_stacks[player.index()] == 0
+    }
+
+    /// Whether the player can still make betting decisions, i.e. hasn't folded and isn't all-in.
+    pub fn can_act(&self, player: Player) -> bool {
+        !self.player_folded[player.index()] && self.player_stacks[player.index()] > 0
+    }
+
+    pub fn acting_player_count(&self) -> usize {
+        (0..N).filter(|&p| self.can_act(Player(p as u8))).count()
+    }
+
+    /// Like `next_active_player`, but also skips players that are all-in.
+    pub fn next_acting_player(&self) -> Option<Player> {
+        self.first_acting_player_from(Player(((self.active_player.0 as usize + 1) % N) as u8))
+    }
+
+    /// Returns the first player at or after `player` who can still act.
+    pub fn first_acting_player_from(&self, player: Player) -> Option<Player> {
+        std::iter::repeat_n(0..N, 2)
+            .flatten()
+            .skip(player.index())
+            .take(N)
+            .find(|&p| self.can_act(Player(p as u8)))
+            .map(|p| Player(p as u8))
+    }
+
+    /// The number of chips the active player must add to match the current bet, limited by their
+    /// stack.
+    pub fn amount_to_call(&self) -> Value {
+        let p = self.active_player.index();
+        (self.current_bet - self.player_committed[p].min(self.current_bet))
+            .min(self.player_stacks[p])
+    }
+
+    /// The smallest total commitment the active player may raise to, ignoring their stack.
+    pub fn min_raise_to(&self) -> Value {
+        self.current_bet + self.last_raise
+    }
+
+    /// The total commitment of the active player if they go all-in.
+    pub fn max_raise_to(&self) -> Value {
+        let p = self.active_player.index();
+        self.player_committed[p] + self.player_stacks[p]
+    }
+
+    /// Moves chips from the active player's stack so that their commitment for this round is
+    /// `amount` (limited by their stack), updating the current bet and minimum raise.
+    /// Returns true if the current bet was increased.
+    pub fn commit_to(&mut self, amount: Value) -> bool {
+        let p = self.active_player.index();
+        let amount = amount.min(self.max_raise_to());
+
+        if amount <= self.player_committed[p] {
+            return false;
+        }
+
+        self.player_stacks[p] -= amount - self.player_committed[p];
+        self.player_committed[p] = amount;
+
+        if amount > self.current_bet {
+            let raise = amount - self.current_bet;
+            // Short all-ins don't change the minimum raise.
+            if raise >= self.last_raise {
+                self.last_raise = raise;
+            }
+            self.current_bet = amount;
+            self.last_aggressor = Some(self.active_player);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Moves all committed chips into the pot at the end of a betting round.
+    pub fn collect_bets(&mut self) {
+        self.pot = self.current_pot();
+        self.player_committed = [0; N];
+        self.current_bet = 0;
+    }
 }
@@ -1,5 +1,8 @@
 use std::convert::{TryFrom, TryInto};
 use std::fmt::{self, Write};
+use std::str::FromStr;
+
+use crate::parse::ParseError;
 
 #[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[repr(u8)]
@@ -31,6 +34,26 @@ impl fmt::Debug for Suit {
     }
 }
 
+impl fmt::Display for Suit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl FromStr for Suit {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "c" | "C" => Ok(Suit::Clubs),
+            "d" | "D" => Ok(Suit::Diamonds),
+            "h" | "H" => Ok(Suit::Hearts),
+            "s" | "S" => Ok(Suit::Spades),
+            _ => Err(ParseError::new(s, 0, "invalid card suit")),
+        }
+    }
+}
+
 impl TryFrom<u8> for Suit {
     type Error = &'static str;
 
@@ -0,0 +1,225 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+usage: psolve estimate <game> [options]
+       psolve solve <game> [options]
+
+games:
+  kuhn                    Kuhn poker
+  leduc                   Leduc Hold'em
+
+options:
+  --players <n>           number of players (default 2)
+  --solver <variant>      cfr, cfr+, dcfr, chance, external or outcome (default cfr+)
+  --iterations <n>        stop after this many iterations
+  --time <seconds>        stop after this much time
+  --seed <n>              seed for the sampling solvers' rng (default 0)
+  --arena-size <bytes>    arena capacity, with an optional k, m or g suffix
+                          (default: the estimated size of the tree)
+  --report <n>            print exploitability every n iterations (default: only at the end)
+  --output <file>         write the strategy to a file
+  --format <format>       json or csv (default: from the output file's extension, or json)
+  --history <history>     print the strategy after this history, which starts with the
+                          deal, like \"QJ k b\" in kuhn or \"QJ k b c / K\" in leduc
+
+Solves run for 10000 iterations if neither --iterations nor --time is given.";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Estimate,
+    Solve,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SolverVariant {
+    Cfr,
+    CfrPlus,
+    DiscountedCfr,
+    ChanceSampling,
+    ExternalSampling,
+    OutcomeSampling,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Json,
+    Csv,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Args {
+    pub command: Command,
+    pub game: String,
+    pub players: usize,
+    pub solver: SolverVariant,
+    pub iterations: Option<usize>,
+    pub time: Option<Duration>,
+    pub seed: u64,
+    pub arena_size: Option<usize>,
+    /// 0 reports only at the end.
+    pub report_interval: usize,
+    pub output: Option<PathBuf>,
+    pub format: Format,
+    pub history: Option<String>,
+}
+
+impl Args {
+    pub fn parse<I>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut args = args.into_iter();
+
+        let command = match args.next().as_deref() {
+            Some("estimate") => Command::Estimate,
+            Some("solve") => Command::Solve,
+            Some(command) => return Err(format!("unknown command {}", command)),
+            None => return Err("missing command".to_string()),
+        };
+
+        let game = args.next().ok_or("missing game")?;
+
+        let mut parsed = Args {
+            command,
+            game,
+            players: 2,
+            solver: SolverVariant::CfrPlus,
+            iterations: None,
+            time: None,
+            seed: 0,
+            arena_size: None,
+            report_interval: 0,
+            output: None,
+            format: Format::Json,
+            history: None,
+        };
+
+        let mut format = None;
+
+        while let Some(option) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", option))
+            };
+
+            match option.as_str() {
+                "--players" => parsed.players = parse_number(&value()?)?,
+                "--solver" => parsed.solver = parse_solver(&value()?)?,
+                "--iterations" => parsed.iterations = Some(parse_number(&value()?)?),
+                "--time" => {
+                    let value = value()?;
+                    let time = value
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(|s| Duration::try_from_secs_f64(s).ok())
+                        .ok_or_else(|| format!("invalid time {}", value))?;
+                    parsed.time = Some(time);
+                }
+                "--seed" => parsed.seed = parse_number(&value()?)?,
+                "--arena-size" => parsed.arena_size = Some(parse_size(&value()?)?),
+                "--report" => parsed.report_interval = parse_number(&value()?)?,
+                "--output" => parsed.output = Some(PathBuf::from(value()?)),
+                "--format" => {
+                    format = Some(match value()?.as_str() {
+                        "json" => Format::Json,
+                        "csv" => Format::Csv,
+                        other => return Err(format!("unknown format {}", other)),
+                    })
+                }
+                "--history" => parsed.history = Some(value()?),
+                _ => return Err(format!("unknown option {}", option)),
+            }
+        }
+
+        let extension = parsed.output.as_ref().and_then(|o| o.extension());
+        parsed.format = match (format, extension) {
+            (Some(format), _) => format,
+            (None, Some(extension)) if extension == "csv" => Format::Csv,
+            (None, _) => Format::Json,
+        };
+
+        if parsed.command == Command::Solve && parsed.iterations.is_none() && parsed.time.is_none()
+        {
+            parsed.iterations = Some(10000);
+        }
+
+        Ok(parsed)
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid number {}", value))
+}
+
+fn parse_solver(value: &str) -> Result<SolverVariant, String> {
+    Ok(match value {
+        "cfr" => SolverVariant::Cfr,
+        "cfr+" => SolverVariant::CfrPlus,
+        "dcfr" => SolverVariant::DiscountedCfr,
+        "chance" => SolverVariant::ChanceSampling,
+        "external" => SolverVariant::ExternalSampling,
+        "outcome" => SolverVariant::OutcomeSampling,
+        _ => return Err(format!("unknown solver {}", value)),
+    })
+}
+
+fn parse_size(value: &str) -> Result<usize, String> {
+    let (number, multiplier) = match value.char_indices().last() {
+        Some((i, 'k')) | Some((i, 'K')) => (&value[..i], 1 << 10),
+        Some((i, 'm')) | Some((i, 'M')) => (&value[..i], 1 << 20),
+        Some((i, 'g')) | Some((i, 'G')) => (&value[..i], 1 << 30),
+        _ => (value, 1),
+    };
+
+    parse_number::<usize>(number)?
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("size {} is too large", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Args, String> {
+        Args::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_parse() {
+        let args = parse(
+            "solve kuhn --players 3 --solver external --time 1.5 --seed 7 \
+             --arena-size 16m --report 100 --output strategy.csv --history k/b",
+        )
+        .unwrap();
+
+        assert_eq!(args.command, Command::Solve);
+        assert_eq!(args.game, "kuhn");
+        assert_eq!(args.players, 3);
+        assert_eq!(args.solver, SolverVariant::ExternalSampling);
+        assert_eq!(args.iterations, None);
+        assert_eq!(args.time, Some(Duration::from_millis(1500)));
+        assert_eq!(args.seed, 7);
+        assert_eq!(args.arena_size, Some(16 << 20));
+        assert_eq!(args.report_interval, 100);
+        assert_eq!(args.format, Format::Csv);
+        assert_eq!(args.history.as_deref(), Some("k/b"));
+
+        let args = parse("solve kuhn").unwrap();
+        assert_eq!(args.iterations, Some(10000));
+        assert_eq!(args.solver, SolverVariant::CfrPlus);
+        assert_eq!(args.format, Format::Json);
+
+        assert!(parse("estimate").is_err());
+        assert!(parse("solve kuhn --players").is_err());
+        assert!(parse("solve kuhn --solver foo").is_err());
+        assert!(parse("solve kuhn --bogus 1").is_err());
+        assert!(parse("solve kuhn --time -1").is_err());
+        assert_eq!(
+            parse("solve kuhn --time 1e30").err().as_deref(),
+            Some("invalid time 1e30")
+        );
+    }
+}
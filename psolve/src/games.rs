@@ -0,0 +1,205 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use game_tree::{
+    allocate_tree, format_history, parse_history, Event, Game, NodeHandle, ParameterMapping,
+    TreeEstimator,
+};
+use kuhn::{KuhnGame, KuhnState};
+use leduc::{LeducGame, LeducState};
+use solver::{
+    best_response, export_strategy, lookup_strategy, Cfr, CfrParameter, CfrPlus, ChanceSamplingCfr,
+    CsvStrategyWriter, DiscountedCfr, ExternalSamplingCfr, JsonStrategyWriter, OutcomeSamplingCfr,
+    Solver,
+};
+use util::arena::Arena;
+
+use crate::args::{Args, Command, Format, SolverVariant};
+
+/// A game that can be run from the command line.
+trait CliGame: Game {
+    /// The state the tree is allocated from, and every iteration starts from.  It deals the
+    /// players' private information at a chance node, so full-width solvers walk every deal on
+    /// each iteration, and sampling solvers sample one.
+    fn root_state() -> Self::State;
+}
+
+impl<const N: usize> CliGame for KuhnGame<N> {
+    fn root_state() -> Self::State {
+        KuhnState::undealt()
+    }
+}
+
+impl CliGame for LeducGame {
+    fn root_state() -> Self::State {
+        LeducState::undealt()
+    }
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    match (args.game.as_str(), args.players) {
+        ("kuhn", 2) => run_game::<KuhnGame<2>, 2>(args),
+        ("kuhn", 3) => run_game::<KuhnGame<3>, 3>(args),
+        ("kuhn", 4) => run_game::<KuhnGame<4>, 4>(args),
+        ("kuhn", players) => Err(format!(
+            "kuhn is available for 2 to 4 players, not {}",
+            players
+        )),
+        ("leduc", 2) => run_game::<LeducGame, 2>(args),
+        ("leduc", players) => Err(format!("leduc is available for 2 players, not {}", players)),
+        (game, _) => Err(format!("unknown game {}", game)),
+    }
+}
+
+fn run_game<G, const N: usize>(args: &Args) -> Result<(), String>
+where
+    G: CliGame,
+{
+    if args.command == Command::Estimate {
+        let estimator = TreeEstimator::<G, CfrParameter>::from_root(G::root_state());
+        println!("{}", estimator.statistics());
+        return Ok(());
+    }
+
+    let seed = args.seed;
+
+    match args.solver {
+        SolverVariant::Cfr => solve::<G, _, N>(args, Cfr::<N>),
+        SolverVariant::CfrPlus => solve::<G, _, N>(args, CfrPlus::<N>),
+        SolverVariant::DiscountedCfr => solve::<G, _, N>(args, DiscountedCfr::<N>::default()),
+        SolverVariant::ChanceSampling => solve::<G, _, N>(args, ChanceSamplingCfr::<N>::new(seed)),
+        SolverVariant::ExternalSampling => {
+            solve::<G, _, N>(args, ExternalSamplingCfr::<N>::new(seed))
+        }
+        SolverVariant::OutcomeSampling => solve::<G, _, N>(
+            args,
+            OutcomeSamplingCfr::<N>::new(seed, OutcomeSamplingCfr::<N>::EXPLORATION),
+        ),
+    }
+}
+
+fn solve<G, S, const N: usize>(args: &Args, mut solver: S) -> Result<(), String>
+where
+    G: CliGame,
+    S: Solver<G, Parameter = CfrParameter>,
+{
+    let root_state = G::root_state();
+
+    let history = match &args.history {
+        Some(history) => Some(
+            parse_history::<G>(&root_state, history)
+                .map_err(|e| format!("invalid history {:?}: {}", history, e))?,
+        ),
+        None => None,
+    };
+
+    let arena_size = args.arena_size.unwrap_or_else(|| {
+        TreeEstimator::<G, CfrParameter>::from_root(root_state.clone())
+            .memory_bounds()
+            .1
+    });
+
+    let arena = Mutex::new(Arena::with_capacity(arena_size));
+    let root = allocate_tree::<G, CfrParameter>(&root_state, &arena)
+        .map_err(|e| format!("could not allocate the tree: {}", e))?;
+
+    let deals = [(root.clone(), 1.0)];
+
+    let start = Instant::now();
+    let report = |iteration: usize, solver: &S| {
+        let exploitability = best_response::<G, S, N>(&deals, solver).exploitability();
+        println!(
+            "iteration {:>10}  {:>9.2}s  exploitability {:.6}",
+            iteration,
+            start.elapsed().as_secs_f64(),
+            exploitability
+        );
+    };
+
+    let mut iteration = 0;
+    loop {
+        let finished = args.iterations.is_some_and(|n| iteration >= n)
+            || args.time.is_some_and(|t| start.elapsed() >= t);
+        if finished {
+            break;
+        }
+
+        solver.iterate(&root, iteration);
+        iteration += 1;
+
+        if args.report_interval > 0 && iteration % args.report_interval == 0 {
+            report(iteration, &solver);
+        }
+    }
+
+    if args.report_interval == 0 || iteration % args.report_interval != 0 {
+        report(iteration, &solver);
+    }
+
+    if let Some(history) = &history {
+        print_strategy::<G, S>(&root, history, &solver)?;
+    }
+
+    if let Some(path) = &args.output {
+        let write_error = |e| format!("could not write {}: {}", path.display(), e);
+
+        let file = BufWriter::new(File::create(path).map_err(write_error)?);
+
+        let mut file = match args.format {
+            Format::Json => {
+                let mut writer = JsonStrategyWriter::new(file).map_err(write_error)?;
+                export_strategy::<G, S, _>(&root, &solver, &mut writer).map_err(write_error)?;
+                writer.finish().map_err(write_error)?
+            }
+            Format::Csv => {
+                let mut writer = CsvStrategyWriter::new(file);
+                export_strategy::<G, S, _>(&root, &solver, &mut writer).map_err(write_error)?;
+                writer.into_inner()
+            }
+        };
+
+        file.flush().map_err(write_error)?;
+    }
+
+    Ok(())
+}
+
+/// Prints the strategy of each information state at the node `history` leads to.
+fn print_strategy<G, S>(
+    root: &NodeHandle<'_, G, S::Parameter>,
+    history: &[Event<G::Action, G::Chance>],
+    solver: &S,
+) -> Result<(), String>
+where
+    G: Game,
+    S: Solver<G>,
+{
+    let formatted = format_history::<G>(history);
+
+    let found = lookup_strategy::<G, S>(root, history, solver)
+        .map_err(|e| format!("could not follow the history {:?}: {}", formatted, e))?;
+
+    if found.actions.is_empty() {
+        return Err(format!("no player acts after the history {:?}", formatted));
+    }
+
+    println!("after {:?}:", formatted);
+    for (i, strategy) in found.strategies.iter().enumerate() {
+        let description =
+            G::ParameterMapping::get_parameter_description(found.node.state(), Some(i));
+
+        print!("  {:<8}", description);
+        for (&action, frequency) in found.actions.iter().zip(strategy) {
+            print!(
+                "  {} {:6.2}%",
+                G::format_event(Event::Action(action)),
+                frequency * 100.0
+            );
+        }
+        println!();
+    }
+
+    Ok(())
+}
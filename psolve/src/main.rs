@@ -0,0 +1,29 @@
+mod args;
+mod games;
+
+use std::env;
+use std::process;
+
+use crate::args::{Args, USAGE};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.is_empty() || args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let args = match Args::parse(args) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("psolve: {}\n\n{}", error, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(error) = games::run(&args) {
+        eprintln!("psolve: {}", error);
+        process::exit(1);
+    }
+}
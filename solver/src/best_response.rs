@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+
+use game_tree::{Game, NodeHandle, ParameterMapping, Stage};
+
+use crate::solver::Solver;
+
+/// The values of a solver's average strategy profile, and of each player's best response to it.
+#[derive(Clone, Copy, Debug)]
+pub struct BestResponse<const N: usize> {
+    /// Each player's expected utility when everyone plays the average strategy.
+    pub values: [f32; N],
+    /// Each player's expected utility when best responding to everyone else's average strategy.
+    pub best_response_values: [f32; N],
+}
+
+impl<const N: usize> BestResponse<N> {
+    /// The total amount the players could gain by deviating from the profile.  Zero at a Nash
+    /// equilibrium.
+    pub fn nash_conv(&self) -> f32 {
+        self.best_response_values
+            .iter()
+            .zip(self.values.iter())
+            .map(|(b, v)| b - v)
+            .sum()
+    }
+
+    /// The average amount a player could gain by deviating from the profile.
+    pub fn exploitability(&self) -> f32 {
+        self.nash_conv() / N as f32
+    }
+}
+
+/// Walks the tree below the root, evaluating the solver's average strategy against each player's
+/// best response.
+///
+/// `deals` are the root reached with each of its possible states, with their probabilities, such
+/// as each way of dealing the players' cards; see `NodeHandle::with_state`.  A root that deals
+/// the cards itself is its only deal, with probability 1.  Only children present in the tree are
+/// considered.
+pub fn best_response<G, S, const N: usize>(
+    deals: &[(NodeHandle<'_, G, S::Parameter>, f32)],
+    solver: &S,
+) -> BestResponse<N>
+where
+    G: Game,
+    S: Solver<G>,
+{
+    let profile = evaluate::<G, S, N>(deals, solver, None);
+
+    let mut values = [0.0; N];
+    let mut best_response_values = [0.0; N];
+
+    for player in 0..N {
+        values[player] = profile.iter().map(|v| v[player]).sum();
+        best_response_values[player] = evaluate::<G, S, N>(deals, solver, Some(player))
+            .iter()
+            .map(|v| v[player])
+            .sum();
+    }
+
+    BestResponse {
+        values,
+        best_response_values,
+    }
+}
+
+/// Returns the utilities of each node, scaled by its weight.  The nodes are the same node of the
+/// tree, reached with different private information.  `responder` plays a best response instead
+/// of the average strategy.
+fn evaluate<G, S, const N: usize>(
+    nodes: &[(NodeHandle<'_, G, S::Parameter>, f32)],
+    solver: &S,
+    responder: Option<usize>,
+) -> Vec<[f32; N]>
+where
+    G: Game,
+    S: Solver<G>,
+{
+    let (node, stage) = match nodes.first() {
+        Some((node, _)) => (node, node.stage()),
+        None => return Vec::new(),
+    };
+
+    if stage.is_terminal() {
+        return nodes
+            .iter()
+            .map(|(node, weight)| {
+                let mut utilities = [0.0; N];
+                G::get_terminal_utilities(node.state(), &mut utilities);
+                utilities.iter_mut().for_each(|u| *u *= weight);
+                utilities
+            })
+            .collect();
+    }
+
+    let mut values = vec![[0.0; N]; nodes.len()];
+
+    let accumulate = |values: &mut Vec<[f32; N]>, child_values: Vec<[f32; N]>| {
+        for (value, child_value) in values.iter_mut().zip(child_values) {
+            value
+                .iter_mut()
+                .zip(child_value.iter())
+                .for_each(|(v, c)| *v += c);
+        }
+    };
+
+    if stage.is_private_chance() {
+        // Every event leads to the same child, and the players can't tell the states apart there,
+        // so they're all evaluated together, and their values are credited to the states they
+        // came from.
+        let mut children = Vec::new();
+        let mut origins = Vec::new();
+
+        for (i, (node, weight)) in nodes.iter().enumerate() {
+            for (chance, child) in node.chance_children() {
+                let chance_weight = G::get_chance_weight(node.state(), chance);
+                children.push((child, weight * chance_weight));
+                origins.push(i);
+            }
+        }
+
+        let child_values = evaluate::<G, S, N>(&children, solver, responder);
+
+        for (i, child_value) in origins.into_iter().zip(child_values) {
+            values[i]
+                .iter_mut()
+                .zip(child_value.iter())
+                .for_each(|(v, c)| *v += c);
+        }
+
+        return values;
+    }
+
+    if stage.is_chance() {
+        for chance in node.chances() {
+            let children = nodes
+                .iter()
+                .map(|(node, weight)| {
+                    let chance_weight = G::get_chance_weight(node.state(), chance.result());
+                    (node.chance_child(&chance), weight * chance_weight)
+                })
+                .collect::<Vec<_>>();
+
+            accumulate(
+                &mut values,
+                evaluate::<G, S, N>(&children, solver, responder),
+            );
+        }
+
+        return values;
+    }
+
+    let player = stage.player_to_act().unwrap();
+
+    let actions = node.actions().collect::<Vec<_>>();
+
+    // The node hasn't been expanded.
+    if actions.is_empty() {
+        return values;
+    }
+
+    if responder == Some(player) {
+        let child_values = actions
+            .iter()
+            .map(|action| {
+                let children = nodes
+                    .iter()
+                    .map(|(node, weight)| (node.action_child(action), *weight))
+                    .collect::<Vec<_>>();
+
+                evaluate::<G, S, N>(&children, solver, responder)
+            })
+            .collect::<Vec<_>>();
+
+        let parameter_indices = nodes
+            .iter()
+            .map(|(node, _)| G::ParameterMapping::get_parameter_index(node.state()))
+            .collect::<Vec<_>>();
+
+        // The responder can't distinguish states with the same parameter index, so it picks the
+        // action that does best across all of them.
+        let mut action_totals = HashMap::new();
+        for (i, &index) in parameter_indices.iter().enumerate() {
+            let totals = action_totals
+                .entry(index)
+                .or_insert_with(|| vec![0.0; actions.len()]);
+
+            for (total, values) in totals.iter_mut().zip(child_values.iter()) {
+                *total += values[i][player];
+            }
+        }
+
+        for (i, index) in parameter_indices.into_iter().enumerate() {
+            let totals: &Vec<f32> = &action_totals[&index];
+            let best = (0..totals.len())
+                .max_by(|&a, &b| totals[a].total_cmp(&totals[b]))
+                .unwrap();
+
+            values[i] = child_values[best][i];
+        }
+    } else {
+        let strategies = nodes
+            .iter()
+            .map(|(node, _)| {
+                let mut strategy = vec![0.0; actions.len()];
+                solver.get_strategy(node, None, &mut strategy);
+                strategy
+            })
+            .collect::<Vec<_>>();
+
+        for (i, action) in actions.iter().enumerate() {
+            let children = nodes
+                .iter()
+                .zip(strategies.iter())
+                .map(|((node, weight), strategy)| (node.action_child(action), weight * strategy[i]))
+                .collect::<Vec<_>>();
+
+            accumulate(
+                &mut values,
+                evaluate::<G, S, N>(&children, solver, responder),
+            );
+        }
+    }
+
+    values
+}
@@ -1,273 +1,558 @@
-use std::marker::PhantomData;
 use std::mem::{self, MaybeUninit};
+use std::sync::Mutex;
 
-use game_tree::{
-    ActionNode, ChanceNode, Event, Game, NodePtr, NodePtrIterator, Parameter, ParameterMapping,
-    Stage,
-};
-use util::volatile::Volatile;
+use game_tree::{Game, NodeHandle, Parameter, ParameterMapping, PersistentParameter, Stage};
+use util::arena::{Arena, SharedAllocator};
+use util::atomic::AtomicF32;
+use util::rng::JKiss32Rng;
 
+use crate::export::ExportParameter;
+use crate::lazy::{expand, LazySolver};
+use crate::parallel::ParallelSolver;
 use crate::solver::Solver;
 
+/// Vanilla CFR, with simultaneous updates and uniform averaging.
 pub struct Cfr<const N: usize>;
 
-impl<const N: usize> Cfr<N> {
-    fn cfr<'a, G>(
-        &'a self,
-        node: NodePtr,
-        state: &G::State,
-        parameter_index: usize,
-        reach_probabilities: ReachProbabilities<N>,
-    ) -> [f32; N]
+impl<const N: usize> CfrVariant for Cfr<N> {
+    type Utilities = [f32; N];
+
+    fn traverser(&self, _iteration: usize) -> Option<usize> {
+        None
+    }
+
+    fn regret_discount(&self, _iteration: usize) -> Option<RegretDiscount> {
+        None
+    }
+
+    fn strategy_weight(&self, _iteration: usize) -> f32 {
+        1.0
+    }
+}
+
+/// The ways CFR variants differ in how they update `CfrParameter`s.  Iterations passed to these
+/// methods start at 0.
+pub(crate) trait CfrVariant {
+    /// `[f32; N]`, for a game of N players.  The solver impls below cover every variant, and
+    /// read N from this.
+    type Utilities;
+
+    /// The only player whose parameters are updated this iteration, if updates alternate between
+    /// players.  `None` updates every player on every iteration.
+    fn traverser(&self, iteration: usize) -> Option<usize>;
+
+    /// What the updated players' cumulative regrets are multiplied by once all of this
+    /// iteration's regrets have been added to them, or `None` to leave them as they are.
+    fn regret_discount(&self, iteration: usize) -> Option<RegretDiscount>;
+
+    /// The weight of this iteration's strategy in the average strategy.
+    fn strategy_weight(&self, iteration: usize) -> f32;
+}
+
+/// Factors for positive and negative cumulative regrets.  A regret of zero can take either.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RegretDiscount {
+    pub positive: f32,
+    pub negative: f32,
+}
+
+impl<G, V, const N: usize> Solver<G> for V
+where
+    G: Game,
+    V: CfrVariant<Utilities = [f32; N]>,
+{
+    type Parameter = CfrParameter;
+
+    fn iterate(&mut self, root: &NodeHandle<'_, G, CfrParameter>, iteration: usize) {
+        iterate::<G, _, Mutex<Arena>, N>(self, None, None, root, iteration);
+    }
+
+    fn get_utilities(&self, node: &NodeHandle<'_, G, CfrParameter>, utilities: &mut [f32])
     where
-        G: 'a + Game,
+        G: Game,
+    {
+        get_average_utilities::<G, N>(node, utilities);
+    }
+
+    fn get_strategy(
+        &self,
+        action_node: &NodeHandle<'_, G, CfrParameter>,
+        alternate_index: Option<usize>,
+        strategy: &mut [f32],
+    ) where
+        G: Game,
     {
-        let stage = G::get_stage(state);
+        get_average_strategy(action_node, alternate_index, strategy);
+    }
+}
+
+impl<G, V, const N: usize> LazySolver<G> for V
+where
+    G: Game,
+    V: CfrVariant<Utilities = [f32; N]>,
+{
+    fn iterate_lazy<'a, A: SharedAllocator>(
+        &mut self,
+        root: &NodeHandle<'a, G, CfrParameter>,
+        iteration: usize,
+        arena: &'a A,
+    ) {
+        iterate::<G, _, _, N>(self, None, Some(arena), root, iteration);
+    }
+}
+
+impl<G, V, const N: usize> ParallelSolver<G> for V
+where
+    G: Game,
+    V: CfrVariant<Utilities = [f32; N]> + Sync,
+{
+    fn iterate_shared<'a, A: SharedAllocator + Sync>(
+        &self,
+        root: &NodeHandle<'a, G, CfrParameter>,
+        iteration: usize,
+        rng: &mut JKiss32Rng,
+        arena: Option<&'a A>,
+    ) {
+        iterate::<G, _, _, N>(self, Some(rng), arena, root, iteration);
+    }
+}
+
+/// Runs one iteration from `root`.  If `rng` is given, a single child of each chance node is
+/// sampled instead of walking them all, and if `arena` is given, nodes are expanded as they're
+/// visited.
+///
+/// An information set can be visited several times in an iteration, once for each state in it.
+/// Every visit plays the strategy from the regrets of previous iterations, so this iteration's
+/// regrets are collected during the traversal and added afterwards, and then the variant's
+/// discount is applied to the sums, once.
+pub(crate) fn iterate<G, V, A, const N: usize>(
+    variant: &V,
+    rng: Option<&mut JKiss32Rng>,
+    arena: Option<&A>,
+    root: &NodeHandle<'_, G, CfrParameter>,
+    iteration: usize,
+) where
+    G: Game,
+    V: CfrVariant,
+    A: SharedAllocator,
+{
+    let mut regrets = Vec::new();
+
+    cfr::<G, V, A, N>(
+        variant,
+        rng,
+        arena,
+        root,
+        ReachProbabilities {
+            players: [1.0; N],
+            chance: 1.0,
+        },
+        iteration,
+        &mut regrets,
+    );
+
+    for (parameters, regret) in regrets {
+        let r = parameters.cumulative_regret.read();
+        parameters.cumulative_regret.write(r + regret);
+    }
 
-        let mut utilities = [0.0; N];
+    if let Some(discount) = variant.regret_discount(iteration) {
+        discount_regrets(root, variant.traverser(iteration), discount);
+    }
+}
 
-        if stage.is_terminal() {
-            G::get_terminal_utilities(state, &mut utilities);
-            return utilities;
+/// Multiplies the cumulative regrets below `node` of `player`, or of every player, by `discount`.
+fn discount_regrets<G>(
+    node: &NodeHandle<'_, G, CfrParameter>,
+    player: Option<usize>,
+    discount: RegretDiscount,
+) where
+    G: Game,
+{
+    let stage = node.stage();
+
+    if stage.is_action() && player.is_none_or(|p| stage.player_to_act() == Some(p)) {
+        for action in node.actions() {
+            for parameter in action.parameters() {
+                let r = parameter.cumulative_regret.read();
+                let factor = if r > 0.0 {
+                    discount.positive
+                } else {
+                    discount.negative
+                };
+                parameter.cumulative_regret.write(r * factor);
+            }
         }
+    }
 
-        if stage.is_chance() {
-            for child in node.children() {
-                let chance = unsafe { (*(child.0 as *mut ChanceNode<G::Chance>)).result };
+    // A private chance node's one child is only walked once.
+    for (_, child) in node.children() {
+        discount_regrets(&child, player, discount);
+    }
+}
+
+/// Returns the utilities of `node`, and pushes the regrets of the players being updated onto
+/// `regrets`.
+fn cfr<'a, G, V, A, const N: usize>(
+    variant: &V,
+    mut rng: Option<&mut JKiss32Rng>,
+    arena: Option<&A>,
+    node: &NodeHandle<'a, G, CfrParameter>,
+    reach_probabilities: ReachProbabilities<N>,
+    iteration: usize,
+    regrets: &mut Vec<(&'a CfrParameter, f32)>,
+) -> [f32; N]
+where
+    G: Game,
+    V: CfrVariant,
+    A: SharedAllocator,
+{
+    let stage = node.stage();
 
-                let weight = G::get_chance_weight(state, chance);
+    let mut utilities = [0.0; N];
 
-                let mut next_state = state.clone();
-                G::advance_state(&mut next_state, Event::Chance(chance));
+    if stage.is_terminal() {
+        G::get_terminal_utilities(node.state(), &mut utilities);
+        return utilities;
+    }
 
-                let next_parameter_index = self.get_parameter_index::<G>(&next_state);
+    expand::<G, _>(node.ptr(), node.state(), arena);
 
-                let chance_utilities = self.cfr::<G>(
-                    child,
-                    &next_state,
-                    next_parameter_index,
-                    reach_probabilities,
-                );
+    if stage.is_chance() {
+        if let Some(rng) = rng {
+            let child = sample_chance_child(node, rng);
 
-                utilities
-                    .iter_mut()
-                    .zip(IntoIterator::into_iter(chance_utilities))
-                    .for_each(|(u, v)| *u += v * weight);
-            }
-        } else {
-            let (child_count, regret_sum) = {
-                let mut count = 0;
-                let mut sum = 0.0;
-
-                self.parameter_iterator::<G>(node, parameter_index)
-                    .map(|p| p.cumulative_regret.read())
-                    .for_each(|r| {
-                        count += 1;
-                        sum += r.max(0.0);
-                    });
-
-                (count, sum)
-            };
+            return cfr::<G, V, A, N>(
+                variant,
+                Some(rng),
+                arena,
+                &child,
+                reach_probabilities,
+                iteration,
+                regrets,
+            );
+        }
 
-            let player = stage.player_to_act().unwrap();
+        for (chance, child) in node.chance_children() {
+            let weight = G::get_chance_weight(node.state(), chance);
+
+            let chance_utilities = cfr::<G, V, A, N>(
+                variant,
+                None,
+                arena,
+                &child,
+                ReachProbabilities {
+                    chance: reach_probabilities.chance * weight,
+                    ..reach_probabilities
+                },
+                iteration,
+                regrets,
+            );
 
-            let mut player_action_utilities = Vec::with_capacity(child_count);
+            utilities
+                .iter_mut()
+                .zip(IntoIterator::into_iter(chance_utilities))
+                .for_each(|(u, v)| *u += v * weight);
+        }
+    } else {
+        let parameter_index = get_parameter_index::<G>(node.state());
 
-            for (child, parameters) in node
-                .children()
-                .zip(self.parameter_iterator::<G>(node, parameter_index))
-            {
-                let action =
-                    unsafe { (*(child.0 as *mut ActionNode<G::Action, CfrParameter>)).action };
+        let (child_count, regret_sum) = {
+            let mut count = 0;
+            let mut sum = 0.0;
 
-                // The strategic frequency of this action.
-                let action_strategy = if regret_sum > 0.0 {
-                    parameters.cumulative_regret.read().max(0.0) / regret_sum
-                } else {
-                    1.0 / child_count as f32
-                };
+            parameter_iterator(node, parameter_index)
+                .map(|p| p.cumulative_regret.read())
+                .for_each(|r| {
+                    count += 1;
+                    sum += r.max(0.0);
+                });
 
-                let next_reach = action_strategy * reach_probabilities.0[player];
+            (count, sum)
+        };
 
-                let mut next_reach_probabilities = reach_probabilities;
-                next_reach_probabilities.0[player] = next_reach;
+        let player = stage.player_to_act().unwrap();
 
-                // Update cumulative strategy.
-                {
-                    let s = parameters.cumulative_strategy.read();
-                    parameters.cumulative_strategy.write(s + next_reach);
-                }
+        let update = variant
+            .traverser(iteration)
+            .is_none_or(|traverser| traverser == player);
 
-                let mut next_state = state.clone();
-                G::advance_state(&mut next_state, Event::Action(action));
+        let mut player_action_utilities = Vec::with_capacity(child_count);
 
-                let next_parameter_index = self.get_parameter_index::<G>(&next_state);
+        for action in node.actions() {
+            let parameters = action.parameter(parameter_index);
+
+            // The strategic frequency of this action.
+            let action_strategy = if regret_sum > 0.0 {
+                parameters.cumulative_regret.read().max(0.0) / regret_sum
+            } else {
+                1.0 / child_count as f32
+            };
 
-                let action_utilities = self.cfr::<G>(
-                    child,
-                    &next_state,
-                    next_parameter_index,
-                    next_reach_probabilities,
-                );
+            let next_reach = action_strategy * reach_probabilities.players[player];
 
-                player_action_utilities.push(action_utilities[player]);
+            let mut next_reach_probabilities = reach_probabilities;
+            next_reach_probabilities.players[player] = next_reach;
 
-                utilities
-                    .iter_mut()
-                    .zip(action_utilities.iter())
-                    .for_each(|(u, v)| *u += v * action_strategy);
+            // Update cumulative strategy.
+            if update {
+                let s = parameters.cumulative_strategy.read();
+                parameters
+                    .cumulative_strategy
+                    .write(s + next_reach * variant.strategy_weight(iteration));
             }
 
+            let action_utilities = cfr::<G, V, A, N>(
+                variant,
+                rng.as_deref_mut(),
+                arena,
+                &node.action_child(&action),
+                next_reach_probabilities,
+                iteration,
+                regrets,
+            );
+
+            player_action_utilities.push(action_utilities[player]);
+
+            utilities
+                .iter_mut()
+                .zip(action_utilities.iter())
+                .for_each(|(u, v)| *u += v * action_strategy);
+        }
+
+        if update {
             let counterfactual_reach_probabilitiy: f32 = {
-                let mut others = reach_probabilities;
-                others.0[player] = 1.0;
-                IntoIterator::into_iter(others.0).product()
+                let mut others = reach_probabilities.players;
+                others[player] = 1.0;
+                reach_probabilities.chance * IntoIterator::into_iter(others).product::<f32>()
             };
 
-            // Update cumulative regret.
-            for (parameters, utility) in self
-                .parameter_iterator::<G>(node, parameter_index)
-                .zip(player_action_utilities)
+            // Collect regret, which is added at the end of the iteration.
+            for (parameters, utility) in
+                parameter_iterator(node, parameter_index).zip(player_action_utilities)
             {
                 let regret = (utility - utilities[player]) * counterfactual_reach_probabilitiy;
-                let r = parameters.cumulative_regret.read();
-                parameters.cumulative_regret.write(r + regret);
+                regrets.push((parameters, regret));
             }
         }
+    }
 
-        utilities
+    utilities
+}
+
+pub(crate) fn get_parameter_index<G>(state: &G::State) -> usize
+where
+    G: Game,
+{
+    if G::get_stage(state).is_action() {
+        G::ParameterMapping::get_parameter_index(state)
+    } else {
+        0
     }
+}
 
-    fn get_parameter_index<G>(&self, state: &G::State) -> usize
-    where
-        G: Game,
-    {
-        G::get_stage(state)
-            .is_action()
-            .then(|| G::ParameterMapping::get_parameter_index(state))
-            .unwrap_or(0)
+/// Samples a chance event with `Game::sample_chance`, and returns the node it leads to.
+pub(crate) fn sample_chance_child<'a, G, P>(
+    node: &NodeHandle<'a, G, P>,
+    rng: &mut JKiss32Rng,
+) -> NodeHandle<'a, G, P>
+where
+    G: Game,
+{
+    let (chance, index) = G::sample_chance(node.state(), rng);
+
+    if node.stage().is_private_chance() {
+        return node
+            .chance_event_child(chance)
+            .expect("private chance node has no child");
     }
 
-    fn parameter_iterator<'a, G>(
-        &'a self,
-        action_node: NodePtr,
-        parameter_index: usize,
-    ) -> impl Iterator<Item = &'a CfrParameter>
-    where
-        G: 'a + Game,
-    {
-        struct ParameterIterator<'a, G> {
-            children: NodePtrIterator,
-            index: usize,
-            _marker: PhantomData<&'a G>,
-        }
+    let child = node
+        .chances()
+        .nth(index)
+        .expect("sampled chance event is not in the tree");
 
-        impl<'a, G> Iterator for ParameterIterator<'a, G>
-        where
-            G: Game,
-        {
-            type Item = &'a CfrParameter;
-
-            fn next(&mut self) -> Option<Self::Item> {
-                self.children
-                    .next()
-                    .map(|n| n.0 as *mut ActionNode<G::Action, CfrParameter>)
-                    .map(|a| unsafe { &*(*a).parameters.add(self.index) })
-            }
-        }
+    debug_assert!(
+        child.result() == chance,
+        "sampled chance event doesn't match its node"
+    );
 
-        ParameterIterator::<G> {
-            children: action_node.children(),
-            index: parameter_index,
-            _marker: PhantomData,
-        }
+    node.chance_child(&child)
+}
+
+/// Fills `strategy` with the current strategy of a node's children, by regret matching.
+pub(crate) fn get_current_strategy<G>(
+    action_node: &NodeHandle<'_, G, CfrParameter>,
+    parameter_index: usize,
+    strategy: &mut Vec<f32>,
+) where
+    G: Game,
+{
+    strategy.clear();
+    strategy.extend(
+        parameter_iterator(action_node, parameter_index)
+            .map(|p| p.cumulative_regret.read().max(0.0)),
+    );
+
+    let regret_sum: f32 = strategy.iter().sum();
+
+    if regret_sum > 0.0 {
+        strategy.iter_mut().for_each(|s| *s /= regret_sum);
+    } else {
+        let uniform = 1.0 / strategy.len() as f32;
+        strategy.iter_mut().for_each(|s| *s = uniform);
     }
 }
 
-impl<G, const N: usize> Solver<G> for Cfr<N>
+pub(crate) fn parameter_iterator<'a, G>(
+    action_node: &NodeHandle<'a, G, CfrParameter>,
+    parameter_index: usize,
+) -> impl Iterator<Item = &'a CfrParameter>
 where
     G: Game,
 {
-    type Parameter = CfrParameter;
+    action_node
+        .actions()
+        .map(move |action| action.parameter(parameter_index))
+}
+
+/// Normalizes the cumulative strategy of a node's children into `strategy`.
+pub(crate) fn get_average_strategy<G>(
+    action_node: &NodeHandle<'_, G, CfrParameter>,
+    alternate_index: Option<usize>,
+    strategy: &mut [f32],
+) where
+    G: Game,
+{
+    let state = action_node.state();
 
-    fn iterate(&mut self, root: NodePtr, state: G::State, _iteration: usize) {
-        self.cfr::<G>(
-            root,
-            &state,
-            self.get_parameter_index::<G>(&state),
-            ReachProbabilities([1.0; N]),
+    let parameter_index = if let Some(index) = alternate_index {
+        assert!(
+            index < G::ParameterMapping::get_parameter_count(state),
+            "parameter index out of range"
         );
+        index
+    } else {
+        get_parameter_index::<G>(state)
+    };
+
+    let (child_count, strategy_sum) = {
+        let mut count = 0;
+        let mut sum = 0.0;
+
+        parameter_iterator(action_node, parameter_index)
+            .map(|p| p.cumulative_strategy.read())
+            .for_each(|p| {
+                count += 1;
+                sum += p;
+            });
+
+        (count, sum)
+    };
+
+    assert_eq!(
+        strategy.len(),
+        child_count,
+        "incorrect size for strategy buffer"
+    );
+
+    if strategy_sum > 0.0 {
+        strategy
+            .iter_mut()
+            .zip(
+                parameter_iterator(action_node, parameter_index)
+                    .map(|p| p.cumulative_strategy.read()),
+            )
+            .for_each(|(s, t)| *s = t / strategy_sum);
+    } else {
+        strategy
+            .iter_mut()
+            .for_each(|s| *s = 1.0 / child_count as f32);
     }
+}
 
-    fn get_utilities(&self, node: NodePtr, state: &G::State, utilities: &mut [f32])
-    where
-        G: Game,
-    {
-        unimplemented!()
-    }
+/// Writes the expected utilities of `node` into `utilities`, with every player following the
+/// average strategy.
+pub(crate) fn get_average_utilities<G, const N: usize>(
+    node: &NodeHandle<'_, G, CfrParameter>,
+    utilities: &mut [f32],
+) where
+    G: Game,
+{
+    assert_eq!(utilities.len(), N, "incorrect size for utilities buffer");
 
-    fn get_strategy(
-        &self,
-        action_node: NodePtr,
-        state: &G::State,
-        alternate_index: Option<usize>,
-        strategy: &mut [f32],
-    ) where
-        G: Game,
-    {
-        let parameter_index = if let Some(index) = alternate_index {
-            assert!(
-                index < G::ParameterMapping::get_parameter_count(state),
-                "parameter index out of range"
-            );
-            index
-        } else {
-            self.get_parameter_index::<G>(state)
-        };
+    utilities.copy_from_slice(&average_utilities::<G, N>(node));
+}
 
-        let (child_count, strategy_sum) = {
-            let mut count = 0;
-            let mut sum = 0.0;
+fn average_utilities<G, const N: usize>(node: &NodeHandle<'_, G, CfrParameter>) -> [f32; N]
+where
+    G: Game,
+{
+    let stage = node.stage();
 
-            self.parameter_iterator::<G>(action_node, parameter_index)
-                .map(|p| p.cumulative_strategy.read())
-                .for_each(|p| {
-                    count += 1;
-                    sum += p;
-                });
+    let mut utilities = [0.0; N];
 
-            (count, sum)
-        };
+    if stage.is_terminal() {
+        G::get_terminal_utilities(node.state(), &mut utilities);
+        return utilities;
+    }
 
-        assert_eq!(
-            strategy.len(),
-            child_count,
-            "incorrect size for strategy buffer"
-        );
+    if stage.is_chance() {
+        for (chance, child) in node.chance_children() {
+            let weight = G::get_chance_weight(node.state(), chance);
 
-        if strategy_sum > 0.0 {
-            strategy
+            let chance_utilities = average_utilities::<G, N>(&child);
+
+            utilities
                 .iter_mut()
-                .zip(
-                    self.parameter_iterator::<G>(action_node, parameter_index)
-                        .map(|p| p.cumulative_strategy.read()),
-                )
-                .for_each(|(s, t)| *s = t / strategy_sum);
-        } else {
-            strategy
+                .zip(IntoIterator::into_iter(chance_utilities))
+                .for_each(|(u, v)| *u += v * weight);
+        }
+    } else {
+        let mut strategy = vec![0.0; node.actions().count()];
+        get_average_strategy(node, None, &mut strategy);
+
+        for (action, action_strategy) in node.actions().zip(strategy) {
+            let action_utilities = average_utilities::<G, N>(&node.action_child(&action));
+
+            utilities
                 .iter_mut()
-                .for_each(|s| *s = 1.0 / child_count as f32);
+                .zip(IntoIterator::into_iter(action_utilities))
+                .for_each(|(u, v)| *u += v * action_strategy);
         }
     }
+
+    utilities
 }
 
+/// Sampled chance nodes aren't counted in `chance`, since the probability of sampling them cancels
+/// out their probability of happening.
 #[derive(Clone, Copy, Debug)]
-struct ReachProbabilities<const N: usize>([f32; N]);
+struct ReachProbabilities<const N: usize> {
+    players: [f32; N],
+    /// Weights of chance events can depend on the players' private information, so the chance
+    /// part of the reach probability has to be included in counterfactual values.
+    chance: f32,
+}
 
 #[derive(Debug)]
 pub struct CfrParameter {
-    pub cumulative_regret: Volatile<f32>,
-    pub cumulative_strategy: Volatile<f32>,
+    pub cumulative_regret: AtomicF32,
+    pub cumulative_strategy: AtomicF32,
+}
+
+// Just two floats.
+unsafe impl PersistentParameter for CfrParameter {
+    const ID: &'static str = "cfr";
+}
+
+impl ExportParameter for CfrParameter {
+    const FIELDS: &'static [&'static str] = &["cumulative_regret", "cumulative_strategy"];
+
+    fn values(&self, values: &mut Vec<f32>) {
+        values.push(self.cumulative_regret.read());
+        values.push(self.cumulative_strategy.read());
+    }
 }
 
 impl Parameter for CfrParameter {
@@ -0,0 +1,103 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use game_tree::{load_tree, save_tree, LoadError, NodeHandle, PersistentGame, PersistentParameter};
+use util::arena::SharedAllocator;
+
+use crate::solver::Solver;
+
+/// Runs each iteration in `iterations` from `root`, and saves the tree to `path` after every
+/// `interval` iterations and once more at the end.
+///
+/// A checkpoint records the next iteration to run, so a solve can be resumed by loading it with
+/// `load_checkpoint`, and calling this again starting from that iteration.  Solver state outside
+/// of the tree's parameters, such as a sampling solver's rng, isn't saved.
+pub fn iterate_with_checkpoints<G, S>(
+    solver: &mut S,
+    root: &NodeHandle<'_, G, S::Parameter>,
+    iterations: Range<usize>,
+    interval: usize,
+    path: &Path,
+) -> io::Result<()>
+where
+    G: PersistentGame,
+    S: Solver<G>,
+    S::Parameter: PersistentParameter,
+{
+    assert!(interval > 0, "checkpoint interval must be at least 1");
+
+    for iteration in iterations.clone() {
+        solver.iterate(root, iteration);
+
+        let done = iteration + 1;
+        if (done - iterations.start).is_multiple_of(interval) && done != iterations.end {
+            save_checkpoint(root, done, path)?;
+        }
+    }
+
+    save_checkpoint(root, iterations.end, path)
+}
+
+/// Saves the tree to `path`, recording `iteration` as the next one to run.  The tree is written
+/// to a temporary file next to it first, named by appending ".tmp" to its file name, and then moved
+/// into place, so an interrupted save doesn't destroy the previous checkpoint.
+pub fn save_checkpoint<G, P>(
+    root: &NodeHandle<'_, G, P>,
+    iteration: usize,
+    path: &Path,
+) -> io::Result<()>
+where
+    G: PersistentGame,
+    P: PersistentParameter,
+{
+    let temporary = temporary_path(path);
+
+    {
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+        save_tree(root, iteration as u64, &mut writer)?;
+        writer.flush()?;
+    }
+
+    fs::rename(&temporary, path)
+}
+
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    PathBuf::from(temporary)
+}
+
+/// Loads a tree saved by `save_checkpoint` into `arena`, and returns its root and the next
+/// iteration to run.
+pub fn load_checkpoint<'a, G, P, A>(
+    root_state: &G::State,
+    arena: &'a A,
+    path: &Path,
+) -> Result<(NodeHandle<'a, G, P>, usize), LoadError>
+where
+    G: PersistentGame,
+    P: PersistentParameter,
+    A: SharedAllocator,
+{
+    let reader = BufReader::new(File::open(path)?);
+    let (root, iteration) = load_tree::<G, P, _, _>(root_state, arena, reader)?;
+    Ok((root, iteration as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temporary_path() {
+        assert_eq!(temporary_path(Path::new("run.a")), Path::new("run.a.tmp"));
+        assert_eq!(temporary_path(Path::new("run.b")), Path::new("run.b.tmp"));
+        assert_eq!(
+            temporary_path(Path::new("dir/x.tmp")),
+            Path::new("dir/x.tmp.tmp")
+        );
+        assert_eq!(temporary_path(Path::new("run")), Path::new("run.tmp"));
+    }
+}
@@ -0,0 +1,270 @@
+use crate::cfr::{CfrVariant, RegretDiscount};
+
+/// CFR+: updates alternate between players, a player's cumulative regrets are floored at zero
+/// after each of its iterations, and its `t`th iteration contributes to the average strategy with
+/// weight `t`.
+pub struct CfrPlus<const N: usize>;
+
+impl<const N: usize> CfrVariant for CfrPlus<N> {
+    type Utilities = [f32; N];
+
+    fn traverser(&self, iteration: usize) -> Option<usize> {
+        Some(iteration % N)
+    }
+
+    fn regret_discount(&self, _iteration: usize) -> Option<RegretDiscount> {
+        Some(RegretDiscount {
+            positive: 1.0,
+            negative: 0.0,
+        })
+    }
+
+    fn strategy_weight(&self, iteration: usize) -> f32 {
+        updates::<N>(iteration)
+    }
+}
+
+/// Discounted CFR (Brown & Sandholm, 2019), with alternating updates.  After a player's `t`th
+/// iteration, counted from 1, its positive cumulative regrets are multiplied by
+/// `t^alpha / (t^alpha + 1)`, its negative ones by `t^beta / (t^beta + 1)`, and its cumulative
+/// strategy by `(t / (t + 1))^gamma`.
+///
+/// The strategy discount is folded into the weight of each new contribution (`t^gamma`), so the
+/// parameter layout is the same as vanilla CFR's.  Each call to `iterate` is one iteration, so
+/// it should start from a root that deals every chance event, like an undealt state.
+pub struct DiscountedCfr<const N: usize> {
+    pub alpha: f32,
+    pub beta: f32,
+    pub gamma: f32,
+}
+
+impl<const N: usize> DiscountedCfr<N> {
+    pub fn new(alpha: f32, beta: f32, gamma: f32) -> Self {
+        Self { alpha, beta, gamma }
+    }
+}
+
+impl<const N: usize> Default for DiscountedCfr<N> {
+    /// The parameters recommended by the paper: alpha = 1.5, beta = 0, gamma = 2.
+    fn default() -> Self {
+        Self::new(1.5, 0.0, 2.0)
+    }
+}
+
+impl<const N: usize> CfrVariant for DiscountedCfr<N> {
+    type Utilities = [f32; N];
+
+    fn traverser(&self, iteration: usize) -> Option<usize> {
+        Some(iteration % N)
+    }
+
+    fn regret_discount(&self, iteration: usize) -> Option<RegretDiscount> {
+        let t = updates::<N>(iteration);
+        let discount = |exponent: f32| {
+            let p = t.powf(exponent);
+            p / (p + 1.0)
+        };
+
+        Some(RegretDiscount {
+            positive: discount(self.alpha),
+            negative: discount(self.beta),
+        })
+    }
+
+    fn strategy_weight(&self, iteration: usize) -> f32 {
+        updates::<N>(iteration).powf(self.gamma)
+    }
+}
+
+/// How many times the player updated on `iteration` has been updated, counting this iteration.
+fn updates<const N: usize>(iteration: usize) -> f32 {
+    (iteration / N + 1) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use rand::Rng;
+
+    use game_tree::{allocate_tree, Event, Game, ParameterMapping, Stage};
+    use util::arena::Arena;
+
+    use super::*;
+    use crate::cfr::CfrParameter;
+    use crate::solver::Solver;
+
+    /// A coin that only the second player sees is flipped, and the first player, who can't tell
+    /// heads from tails, picks left or right.  The second player never acts.
+    #[derive(Clone, Copy, Debug)]
+    struct GuessState {
+        heads: Option<bool>,
+        left: Option<bool>,
+    }
+
+    /// `Some(true)` for the first player's turn, `Some(false)` for the flip, `None` at the end.
+    struct GuessStage(Option<bool>);
+
+    impl Stage for GuessStage {
+        fn is_action(&self) -> bool {
+            self.0 == Some(true)
+        }
+        fn is_chance(&self) -> bool {
+            self.0 == Some(false)
+        }
+        fn is_terminal(&self) -> bool {
+            self.0.is_none()
+        }
+        fn is_private_chance(&self) -> bool {
+            self.is_chance()
+        }
+        fn player_to_act(&self) -> Option<usize> {
+            self.is_action().then_some(0)
+        }
+    }
+
+    struct GuessGame;
+
+    impl Game for GuessGame {
+        type Action = bool;
+        type Chance = bool;
+        type ParameterMapping = GuessGame;
+        type Stage = GuessStage;
+        type State = GuessState;
+
+        fn advance_state(state: &mut GuessState, event: Event<bool, bool>) {
+            match event {
+                Event::Chance(heads) => state.heads = Some(heads),
+                Event::Action(left) => state.left = Some(left),
+            }
+        }
+
+        fn populate_events(state: &GuessState, events: &mut Vec<Event<bool, bool>>) {
+            events.clear();
+            match (state.heads, state.left) {
+                (None, _) => events.extend([Event::Chance(true), Event::Chance(false)]),
+                (Some(_), None) => events.extend([Event::Action(true), Event::Action(false)]),
+                (Some(_), Some(_)) => (),
+            }
+        }
+
+        fn get_chance_weight(_state: &GuessState, _heads: bool) -> f32 {
+            0.5
+        }
+
+        fn sample_chance<R: Rng>(_state: &GuessState, rng: &mut R) -> (bool, usize) {
+            let heads = rng.gen::<bool>();
+            (heads, if heads { 0 } else { 1 })
+        }
+
+        fn get_stage(state: &GuessState) -> GuessStage {
+            match (state.heads, state.left) {
+                (None, _) => GuessStage(Some(false)),
+                (Some(_), None) => GuessStage(Some(true)),
+                (Some(_), Some(_)) => GuessStage(None),
+            }
+        }
+
+        fn get_branching_hint(state: &GuessState) -> usize {
+            if state.left.is_some() {
+                0
+            } else {
+                2
+            }
+        }
+
+        /// Heads pays 1 for left and -1 for right, and tails pays 0 for left and 1 for right.
+        fn get_terminal_utilities(state: &GuessState, utilities: &mut [f32]) {
+            utilities[0] = match (state.heads, state.left) {
+                (Some(true), Some(true)) => 1.0,
+                (Some(true), Some(false)) => -1.0,
+                (Some(false), Some(true)) => 0.0,
+                _ => 1.0,
+            };
+            utilities[1] = -utilities[0];
+        }
+    }
+
+    impl ParameterMapping for GuessGame {
+        type State = GuessState;
+
+        fn get_parameter_count(_state: &GuessState) -> usize {
+            1
+        }
+
+        fn get_parameter_index(_state: &GuessState) -> usize {
+            0
+        }
+
+        fn get_parameter_description(
+            _state: &GuessState,
+            _alternate_index: Option<usize>,
+        ) -> String {
+            String::new()
+        }
+    }
+
+    /// The first player's cumulative regrets and strategy for left and right after two of its
+    /// iterations, and the second player's one, from the undealt root.
+    fn solve<S: Solver<GuessGame, Parameter = CfrParameter>>(solver: &mut S) -> [(f32, f32); 2] {
+        let state = GuessState {
+            heads: None,
+            left: None,
+        };
+        let arena = Mutex::new(Arena::with_capacity(1000));
+        let root = allocate_tree::<GuessGame, CfrParameter>(&state, &arena).unwrap();
+
+        for i in 0..3 {
+            solver.iterate(&root, i);
+        }
+
+        let (_, guess) = root.children().next().unwrap();
+        let mut values = guess.actions().map(|a| {
+            let p = a.parameter(0);
+            (p.cumulative_regret.read(), p.cumulative_strategy.read())
+        });
+
+        [values.next().unwrap(), values.next().unwrap()]
+    }
+
+    fn assert_close(actual: [(f32, f32); 2], expected: [(f32, f32); 2]) {
+        for ((r, s), (expected_r, expected_s)) in actual.iter().zip(&expected) {
+            assert!(
+                (r - expected_r).abs() < 1e-6,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+            assert!(
+                (s - expected_s).abs() < 1e-6,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    // Each iteration visits the first player's one information set on heads and on tails, with
+    // chance reach 1/2, and plays the same strategy on both.  On its first iteration, left and right
+    // are equally likely, for regrets of (0.5, -0.5) on heads and (-0.25, 0.25) on tails, which sum
+    // to (0.25, -0.25).  On its second, left is always picked, for regrets of (0, -1) on heads and
+    // (0, 0.5) on tails, which sum to (0, -0.5).
+
+    #[test]
+    fn test_cfr_plus() {
+        // Floored once after the sums: (0.25, 0) and then (0.25, -0.5) to (0.25, 0).  Strategies
+        // are weighted by 1 and then 2.
+        assert_close(solve(&mut CfrPlus::<2>), [(0.25, 5.0), (0.0, 1.0)]);
+    }
+
+    #[test]
+    fn test_discounted_cfr() {
+        // Halved at t = 1: (0.125, -0.125).  At t = 2, (0.125, -0.625) is multiplied by
+        // 2^1.5 / (2^1.5 + 1) and 1/2.  Strategies are weighted by 1 and then 2^2.
+        let t = 2f32.powf(1.5);
+        assert_close(
+            solve(&mut DiscountedCfr::<2>::default()),
+            [(0.125 * t / (t + 1.0), 9.0), (-0.3125, 1.0)],
+        );
+    }
+}
@@ -0,0 +1,364 @@
+use std::io::{self, Write};
+
+use game_tree::{format_history, Event, Game, NodeHandle, ParameterMapping, Stage};
+
+use crate::solver::Solver;
+
+/// The frequency of one action at one information state.
+pub struct StrategyRecord<'a, A, C, P> {
+    /// The events leading to the node the information state belongs to.
+    pub history: &'a [Event<A, C>],
+    pub player: usize,
+    pub parameter_index: usize,
+    pub description: &'a str,
+    pub action: A,
+    pub frequency: f32,
+    /// The action's parameter for this information state.  It might not be present if the node
+    /// doesn't have all of its actions expanded.
+    pub parameter: Option<&'a P>,
+}
+
+/// A parameter whose values can be written out by the strategy writers.
+pub trait ExportParameter {
+    /// The names of the values, in the order `values` produces them.
+    const FIELDS: &'static [&'static str];
+
+    fn values(&self, values: &mut Vec<f32>);
+}
+
+/// Walks the tree below `root`, and calls `visit` with the records of each information state, one
+/// for each of its actions, in the order `Game::populate_events` produces them.  Information
+/// states are visited depth first, and every parameter index of a node is visited, whether or not
+/// the state could really reach it.
+pub fn visit_strategy<G, S, F>(
+    root: &NodeHandle<'_, G, S::Parameter>,
+    solver: &S,
+    mut visit: F,
+) -> io::Result<()>
+where
+    G: Game,
+    S: Solver<G>,
+    F: FnMut(&[StrategyRecord<'_, G::Action, G::Chance, S::Parameter>]) -> io::Result<()>,
+{
+    fn descend<'a, G, S, F>(
+        node: NodeHandle<'a, G, S::Parameter>,
+        solver: &S,
+        history: &mut Vec<Event<G::Action, G::Chance>>,
+        visit: &mut F,
+    ) -> io::Result<()>
+    where
+        G: Game,
+        S: Solver<G>,
+        S::Parameter: 'a,
+        F: FnMut(&[StrategyRecord<'_, G::Action, G::Chance, S::Parameter>]) -> io::Result<()>,
+    {
+        let stage = node.stage();
+        let state = node.state();
+
+        if stage.is_terminal() {
+            return Ok(());
+        }
+
+        if stage.is_action() {
+            let player = stage.player_to_act().unwrap();
+
+            let mut events = Vec::with_capacity(G::get_branching_hint(state));
+            G::populate_events(state, &mut events);
+
+            let actions = events
+                .iter()
+                .map(|&e| match e {
+                    Event::Action(a) => a,
+                    _ => panic!("event must be action"),
+                })
+                .collect::<Vec<_>>();
+
+            let action_nodes = actions
+                .iter()
+                .map(|&a| node.actions().find(|b| b.action() == a))
+                .collect::<Vec<_>>();
+
+            let mut strategy = vec![0.0; actions.len()];
+
+            for i in 0..G::ParameterMapping::get_parameter_count(state) {
+                solver.get_strategy(&node, Some(i), &mut strategy);
+
+                let description = G::ParameterMapping::get_parameter_description(state, Some(i));
+
+                let records = actions
+                    .iter()
+                    .zip(&action_nodes)
+                    .zip(&strategy)
+                    .map(|((&action, action_node), &frequency)| StrategyRecord {
+                        history,
+                        player,
+                        parameter_index: i,
+                        description: &description,
+                        action,
+                        frequency,
+                        parameter: action_node.map(|b| b.parameter(i)),
+                    })
+                    .collect::<Vec<_>>();
+
+                visit(&records)?;
+            }
+        }
+
+        if stage.is_private_chance() {
+            // Private events aren't part of the history, and any of them leads to the same
+            // information states.
+            let mut events = Vec::new();
+            G::populate_events(state, &mut events);
+
+            let child = match events.first() {
+                Some(&Event::Chance(chance)) => node.chance_event_child(chance),
+                _ => None,
+            };
+
+            if let Some(child) = child {
+                descend::<G, S, F>(child, solver, history, visit)?;
+            }
+
+            return Ok(());
+        }
+
+        for (event, child) in node.children() {
+            history.push(event);
+            descend::<G, S, F>(child, solver, history, visit)?;
+            history.pop();
+        }
+
+        Ok(())
+    }
+
+    descend::<G, S, F>(root.clone(), solver, &mut Vec::new(), &mut visit)
+}
+
+/// Something strategy records of `G` can be written to.
+pub trait StrategyWriter<G, P>
+where
+    G: Game,
+{
+    /// Writes the records of one information state.
+    fn write_records(
+        &mut self,
+        records: &[StrategyRecord<'_, G::Action, G::Chance, P>],
+    ) -> io::Result<()>;
+}
+
+/// Writes the strategy below `root` to `writer`.
+pub fn export_strategy<G, S, W>(
+    root: &NodeHandle<'_, G, S::Parameter>,
+    solver: &S,
+    writer: &mut W,
+) -> io::Result<()>
+where
+    G: Game,
+    S: Solver<G>,
+    W: StrategyWriter<G, S::Parameter>,
+{
+    visit_strategy::<G, S, _>(root, solver, |records| writer.write_records(records))
+}
+
+/// Writes records as a JSON array of objects, with the history as a history string, and a field
+/// for each of the parameter's values, which are `null` if the parameter isn't present.  Actions
+/// are written as their `Game::format_event` tokens.  `finish` must be called to close the array.
+pub struct JsonStrategyWriter<W> {
+    writer: W,
+    first: bool,
+    values: Vec<f32>,
+}
+
+impl<W: Write> JsonStrategyWriter<W> {
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(b"[")?;
+
+        Ok(Self {
+            writer,
+            first: true,
+            values: Vec::new(),
+        })
+    }
+
+    /// Closes the array, and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.write_all(b"\n]\n")?;
+        Ok(self.writer)
+    }
+}
+
+impl<W, G, P> StrategyWriter<G, P> for JsonStrategyWriter<W>
+where
+    W: Write,
+    G: Game,
+    P: ExportParameter,
+{
+    fn write_records(
+        &mut self,
+        records: &[StrategyRecord<'_, G::Action, G::Chance, P>],
+    ) -> io::Result<()> {
+        for record in records {
+            let w = &mut self.writer;
+
+            w.write_all(if self.first { b"\n  {" } else { b",\n  {" })?;
+            self.first = false;
+
+            w.write_all(b"\"history\": ")?;
+            write_json_string(w, &format_history::<G>(record.history))?;
+
+            write!(
+                w,
+                ", \"player\": {}, \"parameter_index\": {}, \"description\": ",
+                record.player, record.parameter_index
+            )?;
+            write_json_string(w, record.description)?;
+            w.write_all(b", \"action\": ")?;
+            write_json_string(w, &G::format_event(Event::Action(record.action)))?;
+            w.write_all(b", \"frequency\": ")?;
+            write_json_number(w, record.frequency)?;
+
+            self.values.clear();
+            if let Some(parameter) = record.parameter {
+                parameter.values(&mut self.values);
+            }
+
+            for (i, field) in P::FIELDS.iter().enumerate() {
+                write!(w, ", \"{}\": ", field)?;
+                match self.values.get(i) {
+                    Some(&value) => write_json_number(w, value)?,
+                    None => w.write_all(b"null")?,
+                }
+            }
+
+            w.write_all(b"}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes records as CSV, with a header row, the history as a history string, and a column for
+/// each of the parameter's values, which are empty if the parameter isn't present.  Actions are
+/// written as their `Game::format_event` tokens.
+pub struct CsvStrategyWriter<W> {
+    writer: W,
+    header: bool,
+    values: Vec<f32>,
+}
+
+impl<W: Write> CsvStrategyWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            header: false,
+            values: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W, G, P> StrategyWriter<G, P> for CsvStrategyWriter<W>
+where
+    W: Write,
+    G: Game,
+    P: ExportParameter,
+{
+    fn write_records(
+        &mut self,
+        records: &[StrategyRecord<'_, G::Action, G::Chance, P>],
+    ) -> io::Result<()> {
+        let w = &mut self.writer;
+
+        if !self.header {
+            w.write_all(b"history,player,parameter_index,description,action,frequency")?;
+            for field in P::FIELDS {
+                write!(w, ",{}", field)?;
+            }
+            w.write_all(b"\n")?;
+            self.header = true;
+        }
+
+        for record in records {
+            write_csv_field(w, &format_history::<G>(record.history))?;
+            write!(w, ",{},{},", record.player, record.parameter_index)?;
+            write_csv_field(w, record.description)?;
+            w.write_all(b",")?;
+            write_csv_field(w, &G::format_event(Event::Action(record.action)))?;
+            write!(w, ",{}", record.frequency)?;
+
+            self.values.clear();
+            if let Some(parameter) = record.parameter {
+                parameter.values(&mut self.values);
+            }
+
+            for i in 0..P::FIELDS.len() {
+                match self.values.get(i) {
+                    Some(value) => write!(w, ",{}", value)?,
+                    None => w.write_all(b",")?,
+                }
+            }
+
+            w.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_json_string<W: Write>(writer: &mut W, string: &str) -> io::Result<()> {
+    writer.write_all(b"\"")?;
+
+    for c in string.chars() {
+        match c {
+            '"' => writer.write_all(b"\\\"")?,
+            '\\' => writer.write_all(b"\\\\")?,
+            '\n' => writer.write_all(b"\\n")?,
+            c if (c as u32) < 0x20 => write!(writer, "\\u{:04x}", c as u32)?,
+            c => write!(writer, "{}", c)?,
+        }
+    }
+
+    writer.write_all(b"\"")
+}
+
+/// JSON has no infinities or NaN, so those are written as `null`.
+fn write_json_number<W: Write>(writer: &mut W, value: f32) -> io::Result<()> {
+    if value.is_finite() {
+        write!(writer, "{}", value)
+    } else {
+        writer.write_all(b"null")
+    }
+}
+
+fn write_csv_field<W: Write>(writer: &mut W, field: &str) -> io::Result<()> {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        write!(writer, "\"{}\"", field.replace('"', "\"\""))
+    } else {
+        writer.write_all(field.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escaping() {
+        let mut json = Vec::new();
+        write_json_string(&mut json, "a \"b\"\\\n\u{1}").unwrap();
+        assert_eq!(json, b"\"a \\\"b\\\"\\\\\\n\\u0001\"");
+
+        let mut csv = Vec::new();
+        write_csv_field(&mut csv, "plain").unwrap();
+        csv.push(b',');
+        write_csv_field(&mut csv, "a, \"b\"").unwrap();
+        assert_eq!(csv, b"plain,\"a, \"\"b\"\"\"");
+
+        let mut number = Vec::new();
+        write_json_number(&mut number, f32::NAN).unwrap();
+        assert_eq!(number, b"null");
+    }
+}
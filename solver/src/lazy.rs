@@ -0,0 +1,38 @@
+use game_tree::{expand_node, Game, NodeHandle, NodePtr, Stage};
+use util::arena::SharedAllocator;
+
+use crate::cfr::CfrParameter;
+use crate::solver::Solver;
+
+/// A solver that can grow its tree as it goes, so that only the parts of the game it visits need
+/// to fit in memory.
+pub trait LazySolver<G>: Solver<G>
+where
+    G: Game,
+{
+    /// The same as `Solver::iterate`, but nodes are given children from `arena` the first time
+    /// they're visited, so `root` can start out as a lone node from `allocate_root_node`.  The
+    /// arena has to live as long as the tree, since the tree ends up holding its nodes.
+    ///
+    /// Panics if the arena runs out of memory.
+    fn iterate_lazy<'a, A: SharedAllocator>(
+        &mut self,
+        root: &NodeHandle<'a, G, Self::Parameter>,
+        iteration: usize,
+        arena: &'a A,
+    );
+}
+
+/// Gives a non-terminal `node` its children if it doesn't have any yet, and if there's an arena
+/// to allocate them from.
+pub(crate) fn expand<G, A>(node: NodePtr, state: &G::State, arena: Option<&A>)
+where
+    G: Game,
+    A: SharedAllocator,
+{
+    if let Some(arena) = arena {
+        if !G::get_stage(state).is_terminal() {
+            expand_node::<G, CfrParameter>(node, state, arena).expect("could not expand node");
+        }
+    }
+}
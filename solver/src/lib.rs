@@ -1,5 +1,24 @@
+mod best_response;
 mod cfr;
+mod checkpoint;
+mod discounted;
+mod export;
+mod lazy;
+mod lookup;
+mod mccfr;
+mod parallel;
 mod solver;
 
+pub use self::best_response::{best_response, BestResponse};
 pub use self::cfr::{Cfr, CfrParameter};
+pub use self::checkpoint::{iterate_with_checkpoints, load_checkpoint, save_checkpoint};
+pub use self::discounted::{CfrPlus, DiscountedCfr};
+pub use self::export::{
+    export_strategy, visit_strategy, CsvStrategyWriter, ExportParameter, JsonStrategyWriter,
+    StrategyRecord, StrategyWriter,
+};
+pub use self::lazy::LazySolver;
+pub use self::lookup::{lookup_strategy, NodeStrategy};
+pub use self::mccfr::{ChanceSamplingCfr, ExternalSamplingCfr, OutcomeSamplingCfr};
+pub use self::parallel::{iterate_parallel, ParallelSolver};
 pub use self::solver::{dump_strategy, Solver};
@@ -0,0 +1,54 @@
+use game_tree::{Event, Game, NodeHandle, ParameterMapping, PathError};
+
+use crate::solver::Solver;
+
+/// A node found by `lookup_strategy`, along with the solver's average strategy there.
+pub struct NodeStrategy<'a, G, P>
+where
+    G: Game,
+{
+    pub node: NodeHandle<'a, G, P>,
+    /// The node's actions, in the order `Game::populate_events` produces them.  Empty if the node
+    /// isn't an action node, or hasn't been expanded.
+    pub actions: Vec<G::Action>,
+    /// The frequency of each action, for each parameter index of the node.
+    pub strategies: Vec<Vec<f32>>,
+}
+
+/// Follows `history` from `root`, and returns the node it leads to, with the strategy of every
+/// information state there.
+///
+/// Events of private chance stages have to be included in `history`, even though they all lead to
+/// the same node.  The strategies don't depend on which one is picked, but the state does.
+#[allow(clippy::type_complexity)]
+pub fn lookup_strategy<'a, G, S>(
+    root: &NodeHandle<'a, G, S::Parameter>,
+    history: &[Event<G::Action, G::Chance>],
+    solver: &S,
+) -> Result<NodeStrategy<'a, G, S::Parameter>, PathError<G::Action, G::Chance>>
+where
+    G: Game,
+    S: Solver<G>,
+{
+    let node = root.follow_path(history)?;
+
+    let actions = node.actions().map(|a| a.action()).collect::<Vec<_>>();
+
+    let strategies = if actions.is_empty() {
+        Vec::new()
+    } else {
+        (0..G::ParameterMapping::get_parameter_count(node.state()))
+            .map(|i| {
+                let mut strategy = vec![0.0; actions.len()];
+                solver.get_strategy(&node, Some(i), &mut strategy);
+                strategy
+            })
+            .collect()
+    };
+
+    Ok(NodeStrategy {
+        node,
+        actions,
+        strategies,
+    })
+}
@@ -0,0 +1,432 @@
+use std::sync::Mutex;
+
+use rand::{Rng, SeedableRng};
+
+use game_tree::{Game, NodeHandle, Stage};
+use util::arena::{Arena, SharedAllocator};
+use util::rng::JKiss32Rng;
+
+use crate::cfr::{
+    get_average_strategy, get_average_utilities, get_current_strategy, get_parameter_index,
+    iterate, parameter_iterator, sample_chance_child, Cfr, CfrParameter,
+};
+use crate::lazy::{expand, LazySolver};
+use crate::solver::Solver;
+
+/// Vanilla CFR that samples a single outcome at each chance node instead of walking them all.
+pub struct ChanceSamplingCfr<const N: usize> {
+    rng: JKiss32Rng,
+}
+
+impl<const N: usize> ChanceSamplingCfr<N> {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: JKiss32Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// Runs one iteration from `root`, expanding nodes as they're visited if `arena` is given.
+    fn iterate_sampled<G, A>(
+        &mut self,
+        root: &NodeHandle<'_, G, CfrParameter>,
+        iteration: usize,
+        arena: Option<&A>,
+    ) where
+        G: Game,
+        A: SharedAllocator,
+    {
+        iterate::<G, _, _, N>(&Cfr::<N>, Some(&mut self.rng), arena, root, iteration);
+    }
+}
+
+impl<G, const N: usize> Solver<G> for ChanceSamplingCfr<N>
+where
+    G: Game,
+{
+    type Parameter = CfrParameter;
+
+    fn iterate(&mut self, root: &NodeHandle<'_, G, CfrParameter>, iteration: usize) {
+        self.iterate_sampled::<G, Mutex<Arena>>(root, iteration, None);
+    }
+
+    fn get_utilities(&self, node: &NodeHandle<'_, G, CfrParameter>, utilities: &mut [f32])
+    where
+        G: Game,
+    {
+        get_average_utilities::<G, N>(node, utilities);
+    }
+
+    fn get_strategy(
+        &self,
+        action_node: &NodeHandle<'_, G, CfrParameter>,
+        alternate_index: Option<usize>,
+        strategy: &mut [f32],
+    ) where
+        G: Game,
+    {
+        get_average_strategy(action_node, alternate_index, strategy);
+    }
+}
+
+impl<G, const N: usize> LazySolver<G> for ChanceSamplingCfr<N>
+where
+    G: Game,
+{
+    fn iterate_lazy<'a, A: SharedAllocator>(
+        &mut self,
+        root: &NodeHandle<'a, G, CfrParameter>,
+        iteration: usize,
+        arena: &'a A,
+    ) {
+        self.iterate_sampled::<G, A>(root, iteration, Some(arena));
+    }
+}
+
+/// External sampling MCCFR.  Each iteration updates one player, alternating, who explores all of
+/// their actions, while chance and the other players' actions are sampled.  The other players'
+/// average strategies are updated at the nodes they're sampled in.
+pub struct ExternalSamplingCfr<const N: usize> {
+    rng: JKiss32Rng,
+}
+
+impl<const N: usize> ExternalSamplingCfr<N> {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: JKiss32Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// Runs one iteration from `root`, expanding nodes as they're visited if `arena` is given.
+    fn iterate_sampled<G, A>(
+        &mut self,
+        root: &NodeHandle<'_, G, CfrParameter>,
+        iteration: usize,
+        arena: Option<&A>,
+    ) where
+        G: Game,
+        A: SharedAllocator,
+    {
+        external_sampling::<G, _, N>(root, iteration % N, arena, &mut self.rng);
+    }
+}
+
+impl<G, const N: usize> Solver<G> for ExternalSamplingCfr<N>
+where
+    G: Game,
+{
+    type Parameter = CfrParameter;
+
+    fn iterate(&mut self, root: &NodeHandle<'_, G, CfrParameter>, iteration: usize) {
+        self.iterate_sampled::<G, Mutex<Arena>>(root, iteration, None);
+    }
+
+    fn get_utilities(&self, node: &NodeHandle<'_, G, CfrParameter>, utilities: &mut [f32])
+    where
+        G: Game,
+    {
+        get_average_utilities::<G, N>(node, utilities);
+    }
+
+    fn get_strategy(
+        &self,
+        action_node: &NodeHandle<'_, G, CfrParameter>,
+        alternate_index: Option<usize>,
+        strategy: &mut [f32],
+    ) where
+        G: Game,
+    {
+        get_average_strategy(action_node, alternate_index, strategy);
+    }
+}
+
+impl<G, const N: usize> LazySolver<G> for ExternalSamplingCfr<N>
+where
+    G: Game,
+{
+    fn iterate_lazy<'a, A: SharedAllocator>(
+        &mut self,
+        root: &NodeHandle<'a, G, CfrParameter>,
+        iteration: usize,
+        arena: &'a A,
+    ) {
+        self.iterate_sampled::<G, A>(root, iteration, Some(arena));
+    }
+}
+
+/// Outcome sampling MCCFR.  Each iteration follows a single path through the tree, and updates
+/// one player, alternating, with regrets importance-weighted by the probability of sampling the
+/// path.  The updated player samples from their current strategy mixed with `exploration` of a
+/// uniform one, and the other players' average strategies are updated along the way.
+pub struct OutcomeSamplingCfr<const N: usize> {
+    rng: JKiss32Rng,
+    exploration: f32,
+}
+
+impl<const N: usize> OutcomeSamplingCfr<N> {
+    /// An exploration that works well in practice.  It was tuned on Kuhn poker, averaging five
+    /// seeds at up to 400,000 iterations: 0.5 to 0.7 converged fastest, and 0.6 was the best of
+    /// those early on.  Much less exploration leaves the updated player's regrets noisy, and much
+    /// more spends most samples on paths that their strategy rarely takes.
+    pub const EXPLORATION: f32 = 0.6;
+
+    pub fn new(seed: u64, exploration: f32) -> Self {
+        assert!(
+            exploration > 0.0 && exploration <= 1.0,
+            "exploration must be in (0, 1]"
+        );
+
+        Self {
+            rng: JKiss32Rng::seed_from_u64(seed),
+            exploration,
+        }
+    }
+
+    /// Runs one iteration from `root`, expanding nodes as they're visited if `arena` is given.
+    fn iterate_sampled<G, A>(
+        &mut self,
+        root: &NodeHandle<'_, G, CfrParameter>,
+        iteration: usize,
+        arena: Option<&A>,
+    ) where
+        G: Game,
+        A: SharedAllocator,
+    {
+        outcome_sampling::<G, _, N>(
+            root,
+            iteration % N,
+            OutcomeReach {
+                others: 1.0,
+                sample: 1.0,
+            },
+            self.exploration,
+            arena,
+            &mut self.rng,
+        );
+    }
+}
+
+impl<G, const N: usize> Solver<G> for OutcomeSamplingCfr<N>
+where
+    G: Game,
+{
+    type Parameter = CfrParameter;
+
+    fn iterate(&mut self, root: &NodeHandle<'_, G, CfrParameter>, iteration: usize) {
+        self.iterate_sampled::<G, Mutex<Arena>>(root, iteration, None);
+    }
+
+    fn get_utilities(&self, node: &NodeHandle<'_, G, CfrParameter>, utilities: &mut [f32])
+    where
+        G: Game,
+    {
+        get_average_utilities::<G, N>(node, utilities);
+    }
+
+    fn get_strategy(
+        &self,
+        action_node: &NodeHandle<'_, G, CfrParameter>,
+        alternate_index: Option<usize>,
+        strategy: &mut [f32],
+    ) where
+        G: Game,
+    {
+        get_average_strategy(action_node, alternate_index, strategy);
+    }
+}
+
+impl<G, const N: usize> LazySolver<G> for OutcomeSamplingCfr<N>
+where
+    G: Game,
+{
+    fn iterate_lazy<'a, A: SharedAllocator>(
+        &mut self,
+        root: &NodeHandle<'a, G, CfrParameter>,
+        iteration: usize,
+        arena: &'a A,
+    ) {
+        self.iterate_sampled::<G, A>(root, iteration, Some(arena));
+    }
+}
+
+/// Returns the traverser's sampled counterfactual utility of `node`.
+fn external_sampling<G, A, const N: usize>(
+    node: &NodeHandle<'_, G, CfrParameter>,
+    traverser: usize,
+    arena: Option<&A>,
+    rng: &mut JKiss32Rng,
+) -> f32
+where
+    G: Game,
+    A: SharedAllocator,
+{
+    let stage = node.stage();
+
+    if stage.is_terminal() {
+        let mut utilities = [0.0; N];
+        G::get_terminal_utilities(node.state(), &mut utilities);
+        return utilities[traverser];
+    }
+
+    expand::<G, _>(node.ptr(), node.state(), arena);
+
+    if stage.is_chance() {
+        let child = sample_chance_child(node, rng);
+        return external_sampling::<G, A, N>(&child, traverser, arena, rng);
+    }
+
+    let player = stage.player_to_act().unwrap();
+    let parameter_index = get_parameter_index::<G>(node.state());
+
+    let mut strategy = Vec::new();
+    get_current_strategy(node, parameter_index, &mut strategy);
+
+    if player != traverser {
+        for (parameters, s) in parameter_iterator(node, parameter_index).zip(&strategy) {
+            let cumulative = parameters.cumulative_strategy.read();
+            parameters.cumulative_strategy.write(cumulative + s);
+        }
+
+        let action = node.actions().nth(sample_index(&strategy, rng)).unwrap();
+        let child = node.action_child(&action);
+        return external_sampling::<G, A, N>(&child, traverser, arena, rng);
+    }
+
+    let action_utilities = node
+        .actions()
+        .map(|action| {
+            let child = node.action_child(&action);
+            external_sampling::<G, A, N>(&child, traverser, arena, rng)
+        })
+        .collect::<Vec<_>>();
+
+    let utility: f32 = action_utilities
+        .iter()
+        .zip(&strategy)
+        .map(|(u, s)| u * s)
+        .sum();
+
+    for (parameters, u) in parameter_iterator(node, parameter_index).zip(action_utilities) {
+        let regret = parameters.cumulative_regret.read();
+        parameters.cumulative_regret.write(regret + u - utility);
+    }
+
+    utility
+}
+
+#[derive(Clone, Copy, Debug)]
+struct OutcomeReach {
+    /// The product of the other players' reach probabilities.
+    others: f32,
+    /// The probability of sampling the path so far.
+    sample: f32,
+}
+
+/// Returns the traverser's utility at the sampled terminal, divided by the probability of
+/// sampling it, and the probability of reaching that terminal from `node`.
+fn outcome_sampling<G, A, const N: usize>(
+    node: &NodeHandle<'_, G, CfrParameter>,
+    traverser: usize,
+    reach: OutcomeReach,
+    exploration: f32,
+    arena: Option<&A>,
+    rng: &mut JKiss32Rng,
+) -> (f32, f32)
+where
+    G: Game,
+    A: SharedAllocator,
+{
+    let stage = node.stage();
+
+    if stage.is_terminal() {
+        let mut utilities = [0.0; N];
+        G::get_terminal_utilities(node.state(), &mut utilities);
+        return (utilities[traverser] / reach.sample, 1.0);
+    }
+
+    expand::<G, _>(node.ptr(), node.state(), arena);
+
+    if stage.is_chance() {
+        // Chance is sampled on-policy, so its probabilities cancel out.
+        let child = sample_chance_child(node, rng);
+        return outcome_sampling::<G, A, N>(&child, traverser, reach, exploration, arena, rng);
+    }
+
+    let player = stage.player_to_act().unwrap();
+    let parameter_index = get_parameter_index::<G>(node.state());
+
+    let mut strategy = Vec::new();
+    get_current_strategy(node, parameter_index, &mut strategy);
+
+    let (index, sample_probability) = if player == traverser {
+        let uniform = 1.0 / strategy.len() as f32;
+        let explore = rng.gen::<f32>() < exploration;
+
+        let index = if explore {
+            rng.gen_range(0..strategy.len())
+        } else {
+            sample_index(&strategy, rng)
+        };
+
+        (
+            index,
+            exploration * uniform + (1.0 - exploration) * strategy[index],
+        )
+    } else {
+        let index = sample_index(&strategy, rng);
+        (index, strategy[index])
+    };
+
+    let action = node.actions().nth(index).unwrap();
+    let child = node.action_child(&action);
+
+    let mut next_reach = reach;
+    next_reach.sample *= sample_probability;
+    if player != traverser {
+        next_reach.others *= strategy[index];
+    }
+
+    let (utility, tail) =
+        outcome_sampling::<G, A, N>(&child, traverser, next_reach, exploration, arena, rng);
+
+    if player == traverser {
+        let weight = utility * reach.others;
+
+        for (i, parameters) in parameter_iterator(node, parameter_index).enumerate() {
+            let regret = if i == index {
+                weight * tail * (1.0 - strategy[index])
+            } else {
+                -weight * tail * strategy[index]
+            };
+
+            let r = parameters.cumulative_regret.read();
+            parameters.cumulative_regret.write(r + regret);
+        }
+    } else {
+        // Stochastically-weighted averaging: `reach.others` includes the acting player's own
+        // reach, and dividing by the probability of sampling the path makes the expected weight
+        // of each visit the acting player's reach, as in full-width CFR.
+        for (parameters, s) in parameter_iterator(node, parameter_index).zip(&strategy) {
+            let cumulative = parameters.cumulative_strategy.read();
+            parameters
+                .cumulative_strategy
+                .write(cumulative + reach.others * s / reach.sample);
+        }
+    }
+
+    (utility, tail * strategy[index])
+}
+
+/// Samples an index from a probability distribution.
+fn sample_index(distribution: &[f32], rng: &mut JKiss32Rng) -> usize {
+    let mut x = rng.gen::<f32>();
+
+    for (i, &p) in distribution.iter().enumerate() {
+        if x < p {
+            return i;
+        }
+        x -= p;
+    }
+
+    // Rounding error can leave a little left over.
+    distribution.iter().rposition(|&p| p > 0.0).unwrap()
+}
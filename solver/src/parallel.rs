@@ -0,0 +1,96 @@
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use rand::SeedableRng;
+
+use game_tree::{Game, NodeHandle};
+use util::arena::SharedAllocator;
+use util::rng::JKiss32Rng;
+
+use crate::solver::Solver;
+
+/// A solver whose iterations can run concurrently on the same tree.
+pub trait ParallelSolver<G>: Solver<G> + Sync
+where
+    G: Game,
+{
+    /// The same as `Solver::iterate`, but safe to call from several threads at once, and a single
+    /// outcome of each chance node is sampled with `rng` instead of walking them all.  If `arena`
+    /// is given, nodes are expanded as they're visited, like `LazySolver::iterate_lazy`.
+    fn iterate_shared<'a, A: SharedAllocator + Sync>(
+        &self,
+        root: &NodeHandle<'a, G, Self::Parameter>,
+        iteration: usize,
+        rng: &mut JKiss32Rng,
+        arena: Option<&'a A>,
+    );
+}
+
+/// Runs each iteration in `iterations` exactly once from `root`, spread across `threads` threads,
+/// and returns when they're all finished.  Each iteration samples chance with the running thread's
+/// rng, and thread `i`'s rng is seeded with `seed + i`, so a root that deals the cards itself
+/// gets a random deal every iteration.  Which thread runs which iteration isn't deterministic.
+///
+/// Threads update the tree's parameters Hogwild-style, without locks.  Parameters are read and
+/// written with relaxed atomics, so there are no data races, but two threads updating the same
+/// parameter at once can lose one of the updates; sampled iterations rarely collide, and the
+/// solution tolerates the noise.  Everything written to the tree before the call is visible to the
+/// threads, since spawning a thread synchronizes with it.  If `arena` is given, nodes are expanded
+/// as they're visited, and new children are published with release ordering, so threads that
+/// find them also see them initialized.  Otherwise, the tree's structure must not change while
+/// this runs.
+///
+/// With an `AtomicArena`, threads expand nodes without waiting on each other for a lock.
+pub fn iterate_parallel<'a, G, S, A>(
+    solver: &S,
+    root: &NodeHandle<'a, G, S::Parameter>,
+    iterations: Range<usize>,
+    threads: usize,
+    seed: u64,
+    arena: Option<&'a A>,
+) where
+    G: Game,
+    G::State: Sync,
+    S: ParallelSolver<G>,
+    A: SharedAllocator + Sync,
+{
+    assert!(threads > 0, "must run at least one thread");
+
+    let root = SharedRoot(root);
+    let next_iteration = AtomicUsize::new(iterations.start);
+
+    thread::scope(|scope| {
+        for i in 0..threads {
+            let root = &root;
+            let next_iteration = &next_iteration;
+            let end = iterations.end;
+
+            scope.spawn(move || {
+                let mut rng = JKiss32Rng::seed_from_u64(seed.wrapping_add(i as u64));
+
+                loop {
+                    let iteration = next_iteration.fetch_add(1, Ordering::Relaxed);
+                    if iteration >= end {
+                        break;
+                    }
+
+                    solver.iterate_shared(root.0, iteration, &mut rng, arena);
+                }
+            });
+        }
+    });
+}
+
+/// Lets the root be shared with the threads.  Its state is `Sync`, and the nodes it leads to are
+/// only touched through `ParallelSolver::iterate_shared`.
+struct SharedRoot<'r, 'a, G, P>(&'r NodeHandle<'a, G, P>)
+where
+    G: Game;
+
+unsafe impl<G, P> Sync for SharedRoot<'_, '_, G, P>
+where
+    G: Game,
+    G::State: Sync,
+{
+}
@@ -0,0 +1,804 @@
+use std::sync::Mutex;
+
+use rand::SeedableRng;
+
+use game_tree::{
+    allocate_root_node, allocate_tree, check_game, find_root_node, load_tree, save_tree, Event,
+    Game, LoadError, NodeHandle, PathErrorKind, Stage, TreeEstimator,
+};
+use kuhn::{deals, KuhnAction, KuhnGame, KuhnState};
+use solver::{
+    best_response, dump_strategy, export_strategy, iterate_parallel, iterate_with_checkpoints,
+    load_checkpoint, lookup_strategy, BestResponse, Cfr, CfrParameter, CfrPlus, ChanceSamplingCfr,
+    CsvStrategyWriter, DiscountedCfr, ExternalSamplingCfr, JsonStrategyWriter, LazySolver,
+    OutcomeSamplingCfr, Solver,
+};
+use util::arena::{Arena, AtomicArena};
+use util::rng::JKiss32Rng;
+
+type KuhnNode<'a, const N: usize> = NodeHandle<'a, KuhnGame<N>, CfrParameter>;
+
+/// An arena of the whole Kuhn poker tree's estimated size below `root_state`.
+fn tree_arena<const N: usize>(root_state: KuhnState<N>) -> Mutex<Arena> {
+    let size = TreeEstimator::<KuhnGame<N>, CfrParameter>::from_root(root_state)
+        .memory_bounds()
+        .1;
+
+    Mutex::new(Arena::with_capacity(size))
+}
+
+/// A whole Kuhn poker tree, allocated below `root_state` in `arena`.
+struct KuhnTree<'a, const N: usize> {
+    root: KuhnNode<'a, N>,
+}
+
+impl<'a, const N: usize> KuhnTree<'a, N> {
+    fn new(arena: &'a Mutex<Arena>, root_state: KuhnState<N>) -> Self {
+        let root = allocate_tree::<KuhnGame<N>, CfrParameter>(&root_state, arena)
+            .expect("could not allocate tree");
+
+        Self { root }
+    }
+
+    fn is_dealt(&self) -> bool {
+        !KuhnGame::<N>::get_stage(self.root.state()).is_chance()
+    }
+
+    /// Runs `iterations` of `solver`.  Each starts from a random deal, unless the root deals the
+    /// cards itself.
+    fn solve<S>(&self, solver: &mut S, iterations: usize)
+    where
+        S: Solver<KuhnGame<N>, Parameter = CfrParameter>,
+    {
+        let mut rng = JKiss32Rng::seed_from_u64(0);
+
+        for i in 0..iterations {
+            if self.is_dealt() {
+                solver.iterate(&deal(&self.root, KuhnState::random(&mut rng)), i);
+            } else {
+                solver.iterate(&self.root, i);
+            }
+        }
+    }
+
+    /// The roots the tree's strategy is played from, with their probabilities: every deal, or
+    /// the root if it deals the cards itself.
+    fn deals(&self) -> Vec<(KuhnNode<'a, N>, f32)> {
+        if !self.is_dealt() {
+            return vec![(self.root.clone(), 1.0)];
+        }
+
+        every_deal(&self.root)
+    }
+
+    fn best_response<S>(&self, solver: &S) -> BestResponse<N>
+    where
+        S: Solver<KuhnGame<N>, Parameter = CfrParameter>,
+    {
+        best_response::<KuhnGame<N>, _, N>(&self.deals(), solver)
+    }
+}
+
+/// `node`, reached with the cards of `state` instead.
+fn deal<'a, const N: usize>(node: &KuhnNode<'a, N>, state: KuhnState<N>) -> KuhnNode<'a, N> {
+    // Kuhn's betting doesn't depend on the cards, so every deal has the same tree.
+    unsafe { node.with_state(state) }
+}
+
+/// `node` reached with every deal, each as likely as the others.
+fn every_deal<'a, const N: usize>(node: &KuhnNode<'a, N>) -> Vec<(KuhnNode<'a, N>, f32)> {
+    let deals = deals::<N>();
+    let probability = 1.0 / deals.len() as f32;
+
+    deals
+        .into_iter()
+        .map(|cards| (deal(node, KuhnState::from_cards(cards)), probability))
+        .collect()
+}
+
+/// Every cumulative regret and strategy in the tree, in order.
+fn tree_parameters(root: &KuhnNode<'_, 2>) -> Vec<(f32, f32)> {
+    fn walk(node: &KuhnNode<'_, 2>, parameters: &mut Vec<(f32, f32)>) {
+        for action in node.actions() {
+            for p in action.parameters() {
+                parameters.push((p.cumulative_regret.read(), p.cumulative_strategy.read()));
+            }
+
+            walk(&node.action_child(&action), parameters);
+        }
+    }
+
+    let mut parameters = Vec::new();
+    walk(root, &mut parameters);
+    parameters
+}
+
+#[test]
+fn test_conformance() {
+    let dealt = deals::<2>()
+        .into_iter()
+        .map(KuhnState::from_cards)
+        .collect::<Vec<_>>();
+
+    let violations =
+        check_game::<KuhnGame<2>, _, _, 2>(&dealt, |state: &KuhnState<2>, p| state.cards()[p]);
+    assert!(violations.is_empty(), "{}", violations[0]);
+
+    let violations =
+        check_game::<KuhnGame<3>, _, _, 3>(&[KuhnState::undealt()], |state: &KuhnState<3>, p| {
+            state.cards()[p]
+        });
+    assert!(violations.is_empty(), "{}", violations[0]);
+}
+
+#[test]
+fn test_tree_estimate() {
+    const N: usize = 3;
+    let root_state = KuhnState::from_cards([0; N]);
+
+    let estimator = TreeEstimator::<KuhnGame<N>, CfrParameter>::from_root(root_state);
+
+    assert_eq!(estimator.action_nodes(), 24);
+    assert_eq!(estimator.chance_nodes(), 0);
+    assert_eq!(estimator.parameters(), 96);
+    assert_eq!(estimator.memory_bounds(), (1552, 1559));
+}
+
+#[test]
+fn test_tree_statistics() {
+    let root_state = KuhnState::from_cards([0, 1]);
+
+    let estimator = TreeEstimator::<KuhnGame<2>, CfrParameter>::from_root(root_state);
+    let statistics = estimator.statistics();
+
+    assert_eq!(statistics.max_depth(), 3);
+    assert_eq!(statistics.total.action_states, 4);
+    assert_eq!(statistics.total.terminal_states, 5);
+    assert_eq!(statistics.total.action_nodes, estimator.action_nodes());
+    assert_eq!(statistics.total.parameters, estimator.parameters());
+
+    let states = statistics
+        .depths
+        .iter()
+        .map(|d| (d.action_states, d.terminal_states, d.action_nodes))
+        .collect::<Vec<_>>();
+    assert_eq!(states, [(1, 0, 2), (2, 0, 4), (1, 3, 2), (0, 2, 0)]);
+
+    // Player 0 acts at the root and after check-bet, player 1 after either first action.
+    assert_eq!(statistics.players.len(), 2);
+    assert_eq!(statistics.players[0].action_states, 2);
+    assert_eq!(statistics.players[1].action_states, 2);
+
+    assert_eq!(statistics.bytes.total(), estimator.memory_bounds().0);
+}
+
+#[test]
+fn test_tree_allocation_size() {
+    let root_state = KuhnState::from_cards([0; 2]);
+    let memory_bounds =
+        TreeEstimator::<KuhnGame<2>, CfrParameter>::from_root(root_state).memory_bounds();
+
+    let arena = tree_arena(root_state);
+    KuhnTree::new(&arena, root_state);
+    let size = arena.lock().unwrap().len();
+
+    assert!(size >= memory_bounds.0, "tree is smaller than expected");
+    assert!(size <= memory_bounds.1, "tree is larger than expected");
+}
+
+#[test]
+fn test_kuhn_solve() {
+    let root_state = KuhnState::from_cards([0, 1, 2]);
+    let arena = tree_arena(root_state);
+    let tree = KuhnTree::new(&arena, root_state);
+
+    let mut solver = Cfr::<3>;
+    tree.solve(&mut solver, 100000);
+
+    dump_strategy::<KuhnGame<3>, Cfr<3>, CfrParameter>(&tree.root, &solver);
+}
+
+#[test]
+fn test_export_strategy() {
+    let root_state = KuhnState::from_cards([0, 1]);
+    let arena = tree_arena(root_state);
+    let tree = KuhnTree::new(&arena, root_state);
+
+    let mut solver = CfrPlus::<2>;
+    tree.solve(&mut solver, 1000);
+
+    let mut csv = CsvStrategyWriter::new(Vec::new());
+    export_strategy::<KuhnGame<2>, _, _>(&tree.root, &solver, &mut csv).unwrap();
+    let csv = String::from_utf8(csv.into_inner()).unwrap();
+
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines[0],
+        "history,player,parameter_index,description,action,frequency,\
+         cumulative_regret,cumulative_strategy"
+    );
+    // Four information sets, three cards each, two actions.
+    assert_eq!(lines.len(), 1 + 4 * 3 * 2);
+    assert!(lines[1].starts_with(",0,0,J,b,"));
+    assert!(lines.iter().any(|l| l.starts_with("k b,0,1,Q,b,")));
+
+    let mut json = JsonStrategyWriter::new(Vec::new()).unwrap();
+    export_strategy::<KuhnGame<2>, _, _>(&tree.root, &solver, &mut json).unwrap();
+    let json = String::from_utf8(json.finish().unwrap()).unwrap();
+
+    assert!(json.starts_with("[\n  {\"history\": \"\", \"player\": 0, \"parameter_index\": 0"));
+    assert!(json.ends_with("}\n]\n"));
+    assert_eq!(json.matches("\"action\"").count(), 4 * 3 * 2);
+}
+
+/// Solves two-player Kuhn poker, and returns the second player's unique equilibrium frequencies:
+/// betting a jack after a check, and calling a bet with a queen.
+fn solve_second_player<S>(solver: &mut S, iterations: usize) -> (f32, f32)
+where
+    S: Solver<KuhnGame<2>, Parameter = CfrParameter>,
+{
+    let root_state = KuhnState::from_cards([0, 1]);
+    let arena = tree_arena(root_state);
+    let tree = KuhnTree::new(&arena, root_state);
+    tree.solve(solver, iterations);
+
+    let mut strategy = [0.0; 2];
+
+    let child = |action| {
+        let (_, child) = tree
+            .root
+            .children()
+            .find(|(event, _)| *event == Event::Action(action))
+            .unwrap();
+        child
+    };
+
+    solver.get_strategy(&child(KuhnAction::Check), Some(0), &mut strategy);
+    let bluff = strategy[0];
+
+    solver.get_strategy(&child(KuhnAction::Bet), Some(1), &mut strategy);
+    let call = strategy[0];
+
+    (bluff, call)
+}
+
+#[test]
+fn test_kuhn_solve_variants() {
+    let results = [
+        ("cfr", solve_second_player(&mut Cfr::<2>, 50000)),
+        ("cfr+", solve_second_player(&mut CfrPlus::<2>, 50000)),
+        (
+            "dcfr",
+            solve_second_player(&mut DiscountedCfr::<2>::default(), 50000),
+        ),
+    ];
+
+    for (name, (bluff, call)) in results {
+        assert!(
+            (bluff - 1.0 / 3.0).abs() < 0.05,
+            "{} bluffs with a jack {} of the time",
+            name,
+            bluff
+        );
+        assert!(
+            (call - 1.0 / 3.0).abs() < 0.05,
+            "{} calls with a queen {} of the time",
+            name,
+            call
+        );
+    }
+}
+
+#[test]
+fn test_kuhn_exploitability() {
+    let root_state = KuhnState::from_cards([0, 1]);
+    let arena = tree_arena(root_state);
+    let tree = KuhnTree::new(&arena, root_state);
+    let deals = tree.deals();
+
+    let mut solver = CfrPlus::<2>;
+
+    // The uniform strategy of an unsolved tree can be exploited.
+    assert!(tree.best_response(&solver).exploitability() > 0.1);
+
+    for i in 0..20000 {
+        for (deal, _) in &deals {
+            <CfrPlus<2> as Solver<KuhnGame<2>>>::iterate(&mut solver, deal, i);
+        }
+    }
+
+    let result = tree.best_response(&solver);
+
+    assert!(
+        (result.values[0] + 1.0 / 18.0).abs() < 0.005,
+        "incorrect game value {}",
+        result.values[0]
+    );
+    assert!(
+        (result.values[0] + result.values[1]).abs() < 1e-5,
+        "values aren't zero-sum"
+    );
+    assert!(
+        result.exploitability() < 0.005,
+        "exploitability {} is too high",
+        result.exploitability()
+    );
+    // The average strategy's utilities, over every deal, are the profile's values.
+    let mut values = [0.0; 2];
+    for (deal, probability) in &deals {
+        let mut utilities = [0.0; 2];
+        <CfrPlus<2> as Solver<KuhnGame<2>>>::get_utilities(&solver, deal, &mut utilities);
+        values[0] += utilities[0] * probability;
+        values[1] += utilities[1] * probability;
+    }
+    assert!((values[0] - result.values[0]).abs() < 1e-5);
+    assert!((values[1] - result.values[1]).abs() < 1e-5);
+
+    assert!(result
+        .best_response_values
+        .iter()
+        .zip(result.values.iter())
+        .all(|(b, v)| b >= v));
+}
+
+#[test]
+fn test_kuhn_chance_root() {
+    let root_state = KuhnState::<2>::undealt();
+
+    let estimator = TreeEstimator::<KuhnGame<2>, CfrParameter>::from_root(root_state);
+    let dealt =
+        TreeEstimator::<KuhnGame<2>, CfrParameter>::from_root(KuhnState::from_cards([0, 1]));
+
+    // Every deal shares the deal's one child.
+    assert_eq!(estimator.chance_nodes(), 1);
+    assert_eq!(estimator.action_nodes(), dealt.action_nodes());
+    assert_eq!(estimator.parameters(), dealt.parameters());
+
+    let arena = tree_arena(root_state);
+    let tree = KuhnTree::new(&arena, root_state);
+
+    let mut solver = Cfr::<2>;
+    tree.solve(&mut solver, 20000);
+
+    let result = tree.best_response(&solver);
+
+    assert!(
+        (result.values[0] + 1.0 / 18.0).abs() < 0.005,
+        "incorrect game value {}",
+        result.values[0]
+    );
+    assert!(
+        result.exploitability() < 0.005,
+        "exploitability {} is too high",
+        result.exploitability()
+    );
+
+    // The chance node's weights give the same values as weighting each deal below it.
+    let (_, deal_node) = tree.root.children().next().unwrap();
+    let dealt_result = best_response::<KuhnGame<2>, _, 2>(&every_deal(&deal_node), &solver);
+    for i in 0..2 {
+        assert!((result.values[i] - dealt_result.values[i]).abs() < 1e-5);
+        assert!(
+            (result.best_response_values[i] - dealt_result.best_response_values[i]).abs() < 1e-5
+        );
+    }
+
+    let mut utilities = [0.0; 2];
+    <Cfr<2> as Solver<KuhnGame<2>>>::get_utilities(&solver, &tree.root, &mut utilities);
+    assert!((utilities[0] - result.values[0]).abs() < 1e-5);
+    assert!((utilities[1] - result.values[1]).abs() < 1e-5);
+
+    let mut csv = CsvStrategyWriter::new(Vec::new());
+    export_strategy::<KuhnGame<2>, _, _>(&tree.root, &solver, &mut csv).unwrap();
+    let csv = String::from_utf8(csv.into_inner()).unwrap();
+    assert_eq!(csv.lines().count(), 25);
+    assert!(csv.lines().any(|l| l.starts_with("k b,0,1,Q,b,")));
+}
+
+#[test]
+fn test_kuhn_chance_root_sampled() {
+    let root_state = KuhnState::<2>::undealt();
+    let arena = tree_arena(root_state);
+    let tree = KuhnTree::new(&arena, root_state);
+
+    let mut solver = ChanceSamplingCfr::<2>::new(0);
+    tree.solve(&mut solver, 50000);
+
+    let exploitability = tree.best_response(&solver).exploitability();
+    assert!(
+        exploitability < 0.01,
+        "exploitability {} is too high",
+        exploitability
+    );
+}
+
+#[test]
+fn test_lookup_strategy() {
+    use KuhnAction::*;
+
+    let root_state = KuhnState::<2>::undealt();
+    let arena = tree_arena(root_state);
+    let tree = KuhnTree::new(&arena, root_state);
+    let root = &tree.root;
+
+    let mut solver = Cfr::<2>;
+    tree.solve(&mut solver, 1000);
+
+    let deal = Event::Chance([2, 0]);
+    let history = [deal, Event::Action(Check), Event::Action(Bet)];
+
+    let found = lookup_strategy::<KuhnGame<2>, _>(root, &history, &solver).unwrap();
+    assert_eq!(found.node.state().cards(), [2, 0]);
+    assert_eq!(found.actions, [Bet, Check]);
+    assert_eq!(found.strategies.len(), 3);
+
+    for (i, strategy) in found.strategies.iter().enumerate() {
+        let mut expected = [0.0; 2];
+        <Cfr<2> as Solver<KuhnGame<2>>>::get_strategy(&solver, &found.node, Some(i), &mut expected);
+        assert_eq!(strategy[..], expected);
+    }
+
+    // The king always calls.
+    assert!(found.strategies[2][0] > 0.99);
+
+    // Any deal leads to the same node.
+    let other = lookup_strategy::<KuhnGame<2>, _>(
+        root,
+        &[
+            Event::Chance([0, 1]),
+            Event::Action(Check),
+            Event::Action(Bet),
+        ],
+        &solver,
+    )
+    .unwrap();
+    assert_eq!(other.node.ptr().0, found.node.ptr().0);
+    assert_eq!(other.strategies, found.strategies);
+
+    let terminal = lookup_strategy::<KuhnGame<2>, _>(
+        root,
+        &[deal, Event::Action(Bet), Event::Action(Check)],
+        &solver,
+    )
+    .unwrap();
+    assert!(terminal.actions.is_empty());
+    assert!(terminal.strategies.is_empty());
+
+    let error = lookup_strategy::<KuhnGame<2>, _>(
+        root,
+        &[
+            deal,
+            Event::Action(Bet),
+            Event::Action(Bet),
+            Event::Action(Bet),
+        ],
+        &solver,
+    )
+    .err()
+    .unwrap();
+    assert_eq!((error.index, error.kind), (3, PathErrorKind::Illegal));
+    assert_eq!(error.to_string(), "event 3 (Bet) isn't legal");
+
+    let error = lookup_strategy::<KuhnGame<2>, _>(root, &[Event::Action(Check)], &solver)
+        .err()
+        .unwrap();
+    assert_eq!((error.index, error.kind), (0, PathErrorKind::Illegal));
+
+    // A tree that's grown lazily might not have the node yet.
+    let lazy_arena = Mutex::new(Arena::with_capacity(1000));
+    let lazy_root =
+        allocate_root_node::<KuhnGame<2>, CfrParameter>(root_state, &lazy_arena).unwrap();
+    let error = lookup_strategy::<KuhnGame<2>, _>(&lazy_root, &history, &solver)
+        .err()
+        .unwrap();
+    assert_eq!((error.index, error.kind), (0, PathErrorKind::NotExpanded));
+}
+
+#[test]
+fn test_kuhn_solve_parallel() {
+    let root_state = KuhnState::<2>::undealt();
+    let arena = tree_arena(root_state);
+    let tree = KuhnTree::new(&arena, root_state);
+
+    let solver = CfrPlus::<2>;
+
+    // Each thread samples its own deals at the root.  Threads race on the tree, so this isn't
+    // deterministic.  Over 20 runs on one core, the worst game value was off by 0.0004 and the
+    // worst exploitability was 0.0039, so the bounds leave a margin of at least 2.5x.
+    iterate_parallel::<KuhnGame<2>, _, Mutex<Arena>>(&solver, &tree.root, 0..200000, 4, 0, None);
+
+    let result = tree.best_response(&solver);
+
+    assert!(
+        (result.values[0] + 1.0 / 18.0).abs() < 0.01,
+        "incorrect game value {}",
+        result.values[0]
+    );
+    assert!(
+        result.exploitability() < 0.01,
+        "exploitability {} is too high",
+        result.exploitability()
+    );
+}
+
+/// Runs `iterations` with random deals, and returns the exploitability of two-player Kuhn poker's
+/// average strategy.
+fn exploitability<S>(solver: &mut S, iterations: usize) -> f32
+where
+    S: Solver<KuhnGame<2>, Parameter = CfrParameter>,
+{
+    let root_state = KuhnState::from_cards([0, 1]);
+    let arena = tree_arena(root_state);
+    let tree = KuhnTree::new(&arena, root_state);
+    tree.solve(solver, iterations);
+    tree.best_response(solver).exploitability()
+}
+
+#[test]
+fn test_kuhn_solve_sampled() {
+    let results = [
+        (
+            "chance sampling",
+            exploitability(&mut ChanceSamplingCfr::<2>::new(0), 50000),
+        ),
+        (
+            "external sampling",
+            exploitability(&mut ExternalSamplingCfr::<2>::new(0), 200000),
+        ),
+        (
+            "outcome sampling",
+            exploitability(
+                &mut OutcomeSamplingCfr::<2>::new(0, OutcomeSamplingCfr::<2>::EXPLORATION),
+                200000,
+            ),
+        ),
+    ];
+
+    for (name, exploitability) in results {
+        assert!(
+            exploitability < 0.01,
+            "{} exploitability {} is too high",
+            name,
+            exploitability
+        );
+    }
+}
+
+#[test]
+fn test_kuhn_outcome_sampling_converges() {
+    let mut early = OutcomeSamplingCfr::<2>::new(1, OutcomeSamplingCfr::<2>::EXPLORATION);
+    let mut late = OutcomeSamplingCfr::<2>::new(1, OutcomeSamplingCfr::<2>::EXPLORATION);
+
+    let early = exploitability(&mut early, 10000);
+    let late = exploitability(&mut late, 200000);
+
+    assert!(
+        late < early / 2.0,
+        "exploitability went from {} to {}",
+        early,
+        late
+    );
+}
+
+#[test]
+fn test_kuhn_solve_lazy() {
+    let root_state = KuhnState::from_cards([0, 1]);
+
+    let full_size = TreeEstimator::<KuhnGame<2>, CfrParameter>::from_root(root_state)
+        .memory_bounds()
+        .1;
+
+    let arena = Mutex::new(Arena::with_capacity(full_size));
+    let root = allocate_root_node::<KuhnGame<2>, CfrParameter>(root_state, &arena).unwrap();
+
+    let mut solver = ExternalSamplingCfr::<2>::new(0);
+    let mut rng = JKiss32Rng::seed_from_u64(0);
+
+    // The first iteration only expands the nodes it visits.
+    <ExternalSamplingCfr<2> as LazySolver<KuhnGame<2>>>::iterate_lazy(
+        &mut solver,
+        &deal(&root, KuhnState::random(&mut rng)),
+        0,
+        &arena,
+    );
+    assert!(arena.lock().unwrap().len() < full_size);
+
+    for i in 1..200000 {
+        <ExternalSamplingCfr<2> as LazySolver<KuhnGame<2>>>::iterate_lazy(
+            &mut solver,
+            &deal(&root, KuhnState::random(&mut rng)),
+            i,
+            &arena,
+        );
+    }
+
+    let exploitability =
+        best_response::<KuhnGame<2>, _, 2>(&every_deal(&root), &solver).exploitability();
+    assert!(
+        exploitability < 0.01,
+        "exploitability {} is too high",
+        exploitability
+    );
+
+    // Expand a fresh tree from several threads at once, each sampling its own deals.
+    let root_state = KuhnState::<2>::undealt();
+    let full_size = TreeEstimator::<KuhnGame<2>, CfrParameter>::from_root(root_state)
+        .memory_bounds()
+        .1;
+
+    let arena = AtomicArena::new(Arena::with_capacity(full_size * 4));
+    let root = allocate_root_node::<KuhnGame<2>, CfrParameter>(root_state, &arena).unwrap();
+
+    let solver = CfrPlus::<2>;
+
+    // Like test_kuhn_solve_parallel, this isn't deterministic.  Over 20 runs on one core, the
+    // worst exploitability was 0.0032, so the bound leaves a margin of about 3x.
+    iterate_parallel::<KuhnGame<2>, _, _>(&solver, &root, 0..200000, 4, 0, Some(&arena));
+
+    let exploitability =
+        best_response::<KuhnGame<2>, _, 2>(&[(root, 1.0)], &solver).exploitability();
+    assert!(
+        exploitability < 0.01,
+        "exploitability {} is too high",
+        exploitability
+    );
+}
+
+#[test]
+fn test_save_load() {
+    let root_state = KuhnState::from_cards([0, 1]);
+    let arena = tree_arena(root_state);
+    let tree = KuhnTree::new(&arena, root_state);
+    let root = &tree.root;
+    let size = arena.lock().unwrap().capacity();
+
+    tree.solve(&mut CfrPlus::<2>, 1000);
+
+    let mut buffer = Vec::new();
+    save_tree::<KuhnGame<2>, CfrParameter, _>(root, 1000, &mut buffer).unwrap();
+
+    let loaded_arena = Mutex::new(Arena::with_capacity(size));
+    let (loaded_root, iteration) =
+        load_tree::<KuhnGame<2>, CfrParameter, _, _>(&root_state, &loaded_arena, &buffer[..])
+            .unwrap();
+
+    assert_eq!(iteration, 1000);
+    assert_eq!(tree_parameters(root), tree_parameters(&loaded_root));
+
+    // Any shared allocator can be loaded into.
+    let atomic_arena = AtomicArena::new(Arena::with_capacity(size));
+    let (atomic_root, _) =
+        load_tree::<KuhnGame<2>, CfrParameter, _, _>(&root_state, &atomic_arena, &buffer[..])
+            .unwrap();
+    assert_eq!(tree_parameters(root), tree_parameters(&atomic_root));
+
+    // The wrong game.
+    assert!(matches!(
+        load_tree::<KuhnGame<3>, CfrParameter, _, _>(
+            &KuhnState::from_cards([0, 1, 2]),
+            &Mutex::new(Arena::with_capacity(size * 4)),
+            &buffer[..],
+        ),
+        Err(LoadError::Format(_))
+    ));
+
+    // Not a tree.
+    assert!(matches!(
+        load_tree::<KuhnGame<2>, CfrParameter, _, _>(
+            &root_state,
+            &Mutex::new(Arena::with_capacity(size)),
+            &b"garbage, and more garbage"[..],
+        ),
+        Err(LoadError::Format(_))
+    ));
+
+    // Truncated.
+    assert!(matches!(
+        load_tree::<KuhnGame<2>, CfrParameter, _, _>(
+            &root_state,
+            &Mutex::new(Arena::with_capacity(size)),
+            &buffer[..buffer.len() - 1],
+        ),
+        Err(LoadError::Io(_))
+    ));
+
+    // A partially expanded tree only loads what was expanded.
+    let arena = Mutex::new(Arena::with_capacity(size));
+    let root = allocate_root_node::<KuhnGame<2>, CfrParameter>(root_state, &arena).unwrap();
+    <ExternalSamplingCfr<2> as LazySolver<KuhnGame<2>>>::iterate_lazy(
+        &mut ExternalSamplingCfr::<2>::new(0),
+        &root,
+        0,
+        &arena,
+    );
+
+    let mut buffer = Vec::new();
+    save_tree::<KuhnGame<2>, CfrParameter, _>(&root, 1, &mut buffer).unwrap();
+
+    let loaded_arena = Mutex::new(Arena::with_capacity(size));
+    let (loaded_root, _) =
+        load_tree::<KuhnGame<2>, CfrParameter, _, _>(&root_state, &loaded_arena, &buffer[..])
+            .unwrap();
+
+    assert_eq!(
+        arena.lock().unwrap().len(),
+        loaded_arena.lock().unwrap().len()
+    );
+    assert_eq!(tree_parameters(&root), tree_parameters(&loaded_root));
+}
+
+#[test]
+fn test_checkpoint_resume() {
+    let root_state = KuhnState::<2>::undealt();
+    let path = std::env::temp_dir().join(format!("kuhn-checkpoint-{}", std::process::id()));
+
+    // Uninterrupted.
+    let arena = tree_arena(root_state);
+    let tree = KuhnTree::new(&arena, root_state);
+    iterate_with_checkpoints::<KuhnGame<2>, _>(&mut CfrPlus::<2>, &tree.root, 0..1200, 500, &path)
+        .unwrap();
+
+    // Stopped after 600, and resumed.
+    let interrupted_arena = tree_arena(root_state);
+    let interrupted = KuhnTree::new(&interrupted_arena, root_state);
+    iterate_with_checkpoints::<KuhnGame<2>, _>(
+        &mut CfrPlus::<2>,
+        &interrupted.root,
+        0..600,
+        500,
+        &path,
+    )
+    .unwrap();
+
+    let resumed_arena = Mutex::new(Arena::with_capacity(arena.lock().unwrap().capacity()));
+    let (resumed_root, start) =
+        load_checkpoint::<KuhnGame<2>, CfrParameter, _>(&root_state, &resumed_arena, &path)
+            .unwrap();
+    assert_eq!(start, 600);
+
+    iterate_with_checkpoints::<KuhnGame<2>, _>(
+        &mut CfrPlus::<2>,
+        &resumed_root,
+        start..1200,
+        500,
+        &path,
+    )
+    .unwrap();
+
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(tree_parameters(&tree.root), tree_parameters(&resumed_root));
+}
+
+#[test]
+fn test_file_arena_tree() {
+    let root_state = KuhnState::from_cards([0, 1]);
+    let path = std::env::temp_dir().join(format!("kuhn-arena-{}", std::process::id()));
+
+    let parameters = {
+        let arena = Mutex::new(Arena::create_file(&path, 1 << 20).unwrap());
+        let root = allocate_tree::<KuhnGame<2>, CfrParameter>(&root_state, &arena).unwrap();
+
+        let mut solver = CfrPlus::<2>;
+        let mut rng = JKiss32Rng::seed_from_u64(0);
+        for i in 0..1000 {
+            <CfrPlus<2> as Solver<KuhnGame<2>>>::iterate(
+                &mut solver,
+                &deal(&root, KuhnState::random(&mut rng)),
+                i,
+            );
+        }
+
+        arena.lock().unwrap().flush().unwrap();
+        tree_parameters(&root)
+    };
+
+    let arena = Mutex::new(Arena::open_file(&path).unwrap());
+    // The file holds the tree allocated above, below the same state.
+    let root = unsafe { find_root_node::<KuhnGame<2>, CfrParameter>(root_state, &arena) };
+
+    assert_eq!(tree_parameters(&root), parameters);
+
+    drop(arena);
+    std::fs::remove_file(&path).unwrap();
+}
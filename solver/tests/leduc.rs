@@ -0,0 +1,149 @@
+use std::sync::Mutex;
+
+use rand::SeedableRng;
+
+use game_tree::{allocate_tree, check_game, sample_game, NodeHandle, TreeEstimator};
+use leduc::{LeducGame, LeducState};
+use solver::{best_response, Cfr, CfrParameter, ChanceSamplingCfr, Solver};
+use util::arena::Arena;
+use util::rng::JKiss32Rng;
+
+type LeducNode<'a> = NodeHandle<'a, LeducGame, CfrParameter>;
+
+fn allocate(arena: &Mutex<Arena>) -> LeducNode<'_> {
+    allocate_tree::<LeducGame, CfrParameter>(&LeducState::from_cards([0, 1]), arena)
+        .expect("could not allocate tree")
+}
+
+/// `node`, a node where the cards have just been dealt, reached with each deal.
+fn deals<'a>(node: &LeducNode<'a>) -> Vec<(LeducNode<'a>, f32)> {
+    LeducState::deals()
+        .into_iter()
+        // The betting doesn't depend on the cards, so every deal has the same tree.
+        .map(|(state, probability)| (unsafe { node.with_state(state) }, probability))
+        .collect()
+}
+
+fn arena() -> Mutex<Arena> {
+    let estimator =
+        TreeEstimator::<LeducGame, CfrParameter>::from_root(LeducState::from_cards([0, 1]));
+    Mutex::new(Arena::with_capacity(estimator.memory_bounds().1))
+}
+
+#[test]
+fn test_conformance() {
+    let deals = LeducState::deals()
+        .into_iter()
+        .map(|(state, _)| state)
+        .collect::<Vec<_>>();
+
+    let violations =
+        check_game::<LeducGame, _, _, 2>(&deals, |state: &LeducState, p| state.cards()[p]);
+    assert!(violations.is_empty(), "{}", violations[0]);
+
+    let violations =
+        check_game::<LeducGame, _, _, 2>(&[LeducState::undealt()], |state: &LeducState, p| {
+            state.cards()[p]
+        });
+    assert!(violations.is_empty(), "{}", violations[0]);
+
+    let mut rng = JKiss32Rng::seed_from_u64(0);
+    let violations = sample_game::<LeducGame, _, _, _, _, 2>(
+        LeducState::random,
+        1000,
+        &mut rng,
+        |state: &LeducState, p| state.cards()[p],
+    );
+    assert!(violations.is_empty(), "{}", violations[0]);
+}
+
+#[test]
+fn test_leduc_exploitability() {
+    let arena = arena();
+    let root = allocate(&arena);
+
+    let deals = deals(&root);
+    let mut solver = Cfr::<2>;
+
+    for i in 0..1000 {
+        for (deal, _) in &deals {
+            <Cfr<2> as Solver<LeducGame>>::iterate(&mut solver, deal, i);
+        }
+    }
+
+    let result = best_response::<LeducGame, _, 2>(&deals, &solver);
+
+    // The first player's value in equilibrium is about -0.0856.
+    assert!(
+        (result.values[0] + 0.0856).abs() < 0.005,
+        "incorrect game value {}",
+        result.values[0]
+    );
+    assert!(
+        result.exploitability() < 0.02,
+        "exploitability {} is too high",
+        result.exploitability()
+    );
+}
+
+#[test]
+fn test_leduc_chance_root() {
+    let root_state = LeducState::undealt();
+
+    let estimator = TreeEstimator::<LeducGame, CfrParameter>::from_root(root_state);
+    let arena = Mutex::new(Arena::with_capacity(estimator.memory_bounds().1));
+    let root = allocate_tree::<LeducGame, CfrParameter>(&root_state, &arena)
+        .expect("could not allocate tree");
+
+    // Every iteration walks every deal, so this is deterministic, and reaches an
+    // exploitability of 0.025.
+    let mut solver = Cfr::<2>;
+    for i in 0..2000 {
+        <Cfr<2> as Solver<LeducGame>>::iterate(&mut solver, &root, i);
+    }
+
+    let result = best_response::<LeducGame, _, 2>(&[(root.clone(), 1.0)], &solver);
+
+    assert!(
+        (result.values[0] + 0.0856).abs() < 0.005,
+        "incorrect game value {}",
+        result.values[0]
+    );
+    assert!(
+        result.exploitability() < 0.03,
+        "exploitability {} is too high",
+        result.exploitability()
+    );
+
+    // The deal's weights give the same values as weighting each deal below it.
+    let (_, deal_node) = root.children().next().unwrap();
+    let dealt_result = best_response::<LeducGame, _, 2>(&deals(&deal_node), &solver);
+    for i in 0..2 {
+        assert!((result.values[i] - dealt_result.values[i]).abs() < 1e-5);
+        assert!(
+            (result.best_response_values[i] - dealt_result.best_response_values[i]).abs() < 1e-5
+        );
+    }
+}
+
+#[test]
+fn test_leduc_chance_sampling() {
+    let arena = arena();
+    let root = allocate(&arena);
+
+    let mut solver = ChanceSamplingCfr::<2>::new(0);
+    let mut rng = JKiss32Rng::seed_from_u64(0);
+
+    for i in 0..100000 {
+        // Every deal has the same tree.
+        let deal = unsafe { root.with_state(LeducState::random(&mut rng)) };
+        <ChanceSamplingCfr<2> as Solver<LeducGame>>::iterate(&mut solver, &deal, i);
+    }
+
+    let exploitability = best_response::<LeducGame, _, 2>(&deals(&root), &solver).exploitability();
+    assert!(
+        exploitability < 0.04,
+        "exploitability {} is too high",
+        exploitability
+    );
+}
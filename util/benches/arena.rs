@@ -0,0 +1,66 @@
+//! Compares how fast several threads can allocate from a locked `Arena` and an `AtomicArena`.
+//!
+//! Run with `cargo bench -p util --bench arena`.
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use util::arena::{Arena, AtomicArena, SharedAllocator};
+
+/// Roughly the shape of an action node allocation: a few nodes followed by their parameters.
+const NODES: usize = 3;
+const PARAMETERS: usize = 12;
+const NODE_SIZE: usize = 32;
+
+const ALLOCATIONS_PER_THREAD: usize = 200_000;
+
+fn main() {
+    let bytes_per_thread = ALLOCATIONS_PER_THREAD * (NODES * NODE_SIZE + PARAMETERS * 8 + 16);
+
+    println!(
+        "{:>8} {:>16} {:>16}",
+        "threads", "mutex (M/s)", "atomic (M/s)"
+    );
+
+    for &threads in &[1, 2, 4, 8] {
+        let capacity = threads * bytes_per_thread;
+
+        let mutex = Mutex::new(Arena::with_capacity(capacity));
+        let mutex_time = run(&mutex, threads);
+
+        let atomic = AtomicArena::new(Arena::with_capacity(capacity));
+        let atomic_time = run(&atomic, threads);
+
+        println!(
+            "{:>8} {:>16.2} {:>16.2}",
+            threads,
+            throughput(threads, mutex_time),
+            throughput(threads, atomic_time),
+        );
+    }
+}
+
+fn run<A: SharedAllocator + Sync>(arena: &A, threads: usize) -> Duration {
+    let start = Instant::now();
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                for _ in 0..ALLOCATIONS_PER_THREAD {
+                    let allocation = arena
+                        .allocate_pair::<[u8; NODE_SIZE], u64>(NODES, PARAMETERS)
+                        .expect("arena is too small");
+                    std::hint::black_box(allocation);
+                }
+            });
+        }
+    });
+
+    start.elapsed()
+}
+
+/// Millions of allocations per second.
+fn throughput(threads: usize, time: Duration) -> f64 {
+    (threads * ALLOCATIONS_PER_THREAD) as f64 / time.as_secs_f64() / 1e6
+}
@@ -1,14 +1,100 @@
 use std::fmt;
+use std::io;
 use std::mem::{self, MaybeUninit};
 use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+#[cfg(unix)]
+use std::{fs::OpenOptions, os::unix::io::AsRawFd, path::Path, sync::atomic::AtomicU64};
+
+/// Hands out memory for a game tree.
+#[allow(clippy::len_without_is_empty)]
+pub trait Allocator {
+    /// Reserves space for `n` values of `T`, aligned for `T`, and returns a pointer to it.
+    /// Allocators that only measure how much space would be used return `None`.
+    fn allocate<T>(&mut self, n: usize) -> Result<Option<*mut MaybeUninit<T>>, Error>;
+
+    /// The number of bytes reserved so far, including alignment.
+    fn len(&self) -> usize;
+
+    /// The address of the first allocation, before alignment, or null for allocators that only
+    /// measure.
+    fn base(&self) -> *mut u8;
+}
+
+/// Hands out memory for a game tree to several threads at once.
+#[allow(clippy::len_without_is_empty)]
+pub trait SharedAllocator {
+    /// The same as `Allocator::allocate`.
+    fn allocate<T>(&self, n: usize) -> Result<Option<*mut MaybeUninit<T>>, Error>;
+
+    /// Reserves space for `n` values of `T`, followed by `m` values of `U` with nothing else
+    /// allocated in between, so that allocations from other threads can't split them up.
+    #[allow(clippy::type_complexity)]
+    fn allocate_pair<T, U>(
+        &self,
+        n: usize,
+        m: usize,
+    ) -> Result<Option<(*mut MaybeUninit<T>, *mut MaybeUninit<U>)>, Error>;
+
+    /// The same as `Allocator::len`.
+    fn len(&self) -> usize;
+
+    /// The same as `Allocator::base`.
+    fn base(&self) -> *mut u8;
+}
+
+/// Every allocation takes the lock, so threads allocating at once wait on each other.
+impl<A: Allocator> SharedAllocator for Mutex<A> {
+    fn allocate<T>(&self, n: usize) -> Result<Option<*mut MaybeUninit<T>>, Error> {
+        self.lock().unwrap().allocate(n)
+    }
+
+    fn allocate_pair<T, U>(
+        &self,
+        n: usize,
+        m: usize,
+    ) -> Result<Option<(*mut MaybeUninit<T>, *mut MaybeUninit<U>)>, Error> {
+        let mut allocator = self.lock().unwrap();
+
+        match (allocator.allocate::<T>(n)?, allocator.allocate::<U>(m)?) {
+            (Some(first), Some(second)) => Ok(Some((first, second))),
+            _ => Ok(None),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.lock().unwrap().len()
+    }
+
+    fn base(&self) -> *mut u8 {
+        self.lock().unwrap().base()
+    }
+}
 
 pub struct Arena {
     buffer: *mut u8,
     cur: *mut u8,
     capacity: usize,
     len: usize,
+    backing: Backing,
+}
+
+enum Backing {
+    Heap,
+    #[cfg(unix)]
+    Mapped {
+        mapping: *mut u8,
+        mapping_len: usize,
+        /// Set for arenas backed by a file, where the length is kept for reopening.
+        header: Option<*mut MappedHeader>,
+    },
 }
 
+// The arena owns its buffer, so it can move between threads like the `Vec` it came from.
+unsafe impl Send for Arena {}
+
 #[allow(clippy::len_without_is_empty)]
 impl Arena {
     pub fn with_capacity(bytes: usize) -> Self {
@@ -19,12 +105,189 @@ impl Arena {
             cur: vec.as_mut_ptr(),
             capacity: vec.capacity(),
             len: 0,
+            backing: Backing::Heap,
         };
         mem::forget(vec);
 
         arena
     }
 
+    /// An arena in anonymous memory mapped from the OS.  Pages are only committed as they're
+    /// touched, so `bytes` can be generous.  With `huge_pages`, the mapping uses the system's
+    /// huge pages, which must have been reserved beforehand.
+    #[cfg(unix)]
+    pub fn anonymous(bytes: usize, huge_pages: bool) -> Result<Self, Error> {
+        let mut flags = libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_NORESERVE;
+        let mut mapping_len = bytes.max(1);
+
+        // A huge page mapping has to be unmapped in whole huge pages.
+        #[cfg(target_os = "linux")]
+        if huge_pages {
+            flags |= libc::MAP_HUGETLB;
+
+            let page_size = huge_page_size()?;
+            mapping_len = mapping_len.div_ceil(page_size) * page_size;
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        if huge_pages {
+            return Err(Error::Io(io::Error::from(io::ErrorKind::Unsupported)));
+        }
+
+        let mapping = map(ptr::null_mut(), mapping_len, flags, -1)?;
+
+        Ok(Self {
+            buffer: mapping,
+            cur: mapping,
+            capacity: bytes,
+            len: 0,
+            backing: Backing::Mapped {
+                mapping,
+                mapping_len,
+                header: None,
+            },
+        })
+    }
+
+    /// An arena in a new file at `path`, which is replaced if it exists.  Allocations are
+    /// written through to the file, and can be brought back with `open_file`.
+    #[cfg(unix)]
+    pub fn create_file<P: AsRef<Path>>(path: P, bytes: usize) -> Result<Self, Error> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+
+        let mapping_len = HEADER_SIZE + bytes;
+        file.set_len(mapping_len as u64)?;
+
+        let mapping = map(
+            ptr::null_mut(),
+            mapping_len,
+            libc::MAP_SHARED,
+            file.as_raw_fd(),
+        )?;
+
+        let header = mapping as *mut MappedHeader;
+        unsafe {
+            header.write(MappedHeader {
+                magic: *MAPPED_MAGIC,
+                address: mapping as u64,
+                capacity: bytes as u64,
+                len: 0,
+            });
+        }
+
+        let buffer = unsafe { mapping.add(HEADER_SIZE) };
+
+        Ok(Self {
+            buffer,
+            cur: buffer,
+            capacity: bytes,
+            len: 0,
+            backing: Backing::Mapped {
+                mapping,
+                mapping_len,
+                header: Some(header),
+            },
+        })
+    }
+
+    /// Reopens an arena created by `create_file`, with everything that was allocated in it.
+    ///
+    /// The file is mapped at the same address it was created at, so any pointers stored in it
+    /// are still valid, and the first allocation is where it was before.  Fails with
+    /// `Error::AddressUnavailable` if something else in the process is using that address.
+    #[cfg(unix)]
+    pub fn open_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+
+        let header = {
+            let mut header = MaybeUninit::<MappedHeader>::uninit();
+            let bytes = unsafe {
+                std::slice::from_raw_parts_mut(
+                    header.as_mut_ptr() as *mut u8,
+                    mem::size_of::<MappedHeader>(),
+                )
+            };
+            io::Read::read_exact(&mut &file, bytes)?;
+            unsafe { header.assume_init() }
+        };
+
+        if &header.magic != MAPPED_MAGIC {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an arena file",
+            )));
+        }
+
+        let mapping_len = HEADER_SIZE + header.capacity as usize;
+        if file.metadata()?.len() < mapping_len as u64 {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "arena file is truncated",
+            )));
+        }
+
+        #[cfg(target_os = "linux")]
+        let flags = libc::MAP_SHARED | libc::MAP_FIXED_NOREPLACE;
+        #[cfg(not(target_os = "linux"))]
+        let flags = libc::MAP_SHARED;
+
+        let address = header.address as *mut u8;
+        let mapping = match map(address, mapping_len, flags, file.as_raw_fd()) {
+            Ok(mapping) => mapping,
+            Err(Error::Io(error)) if error.raw_os_error() == Some(libc::EEXIST) => {
+                return Err(Error::AddressUnavailable)
+            }
+            Err(error) => return Err(error),
+        };
+
+        // Without a way to insist, the address is only a hint.
+        if mapping != address {
+            unsafe {
+                libc::munmap(mapping as *mut libc::c_void, mapping_len);
+            }
+            return Err(Error::AddressUnavailable);
+        }
+
+        let buffer = unsafe { mapping.add(HEADER_SIZE) };
+        let len = header.len as usize;
+
+        Ok(Self {
+            buffer,
+            cur: unsafe { buffer.add(len) },
+            capacity: header.capacity as usize,
+            len,
+            backing: Backing::Mapped {
+                mapping,
+                mapping_len,
+                header: Some(mapping as *mut MappedHeader),
+            },
+        })
+    }
+
+    /// Writes a file-backed arena's contents out to the file.  Does nothing for other arenas.
+    pub fn flush(&self) -> Result<(), Error> {
+        #[cfg(unix)]
+        if let Backing::Mapped {
+            mapping,
+            mapping_len,
+            header: Some(_),
+        } = self.backing
+        {
+            let result =
+                unsafe { libc::msync(mapping as *mut libc::c_void, mapping_len, libc::MS_SYNC) };
+            if result != 0 {
+                return Err(Error::Io(io::Error::last_os_error()));
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn capacity(&self) -> usize {
         self.capacity
     }
@@ -33,6 +296,11 @@ impl Arena {
         self.len
     }
 
+    /// The address of the first allocation, before alignment.
+    pub fn base(&self) -> *mut u8 {
+        self.buffer
+    }
+
     pub fn allocate<T>(&mut self, n: usize) -> Result<*mut MaybeUninit<T>, Error> {
         let offset = self.cur.align_offset(mem::align_of::<T>());
         let size = n * mem::size_of::<T>();
@@ -42,6 +310,18 @@ impl Arena {
             let ptr = unsafe { self.cur.add(offset) };
             self.cur = unsafe { ptr.add(size) };
             self.len = new_len;
+
+            #[cfg(unix)]
+            if let Backing::Mapped {
+                header: Some(header),
+                ..
+            } = self.backing
+            {
+                unsafe {
+                    (*header).len = new_len as u64;
+                }
+            }
+
             Ok(ptr as *mut MaybeUninit<T>)
         } else {
             Err(Error::OutOfMemory)
@@ -54,25 +334,252 @@ impl Arena {
     }
 }
 
+impl Allocator for Arena {
+    fn allocate<T>(&mut self, n: usize) -> Result<Option<*mut MaybeUninit<T>>, Error> {
+        Arena::allocate(self, n).map(Some)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn base(&self) -> *mut u8 {
+        self.buffer
+    }
+}
+
 impl Drop for Arena {
     fn drop(&mut self) {
-        let _vec = unsafe { Vec::from_raw_parts(self.buffer, self.len, self.capacity) };
+        match self.backing {
+            Backing::Heap => {
+                let _vec = unsafe { Vec::from_raw_parts(self.buffer, self.len, self.capacity) };
+            }
+            #[cfg(unix)]
+            Backing::Mapped {
+                mapping,
+                mapping_len,
+                ..
+            } => {
+                let result = unsafe { libc::munmap(mapping as *mut libc::c_void, mapping_len) };
+                debug_assert_eq!(
+                    result,
+                    0,
+                    "couldn't unmap the arena: {}",
+                    io::Error::last_os_error()
+                );
+            }
+        }
+    }
+}
+
+/// An arena that threads can allocate from at once without locking.  Allocations bump an atomic
+/// length, so they're as contiguous as an `Arena`'s, but the order of allocations made by
+/// different threads at the same time isn't deterministic.
+pub struct AtomicArena {
+    arena: Arena,
+    len: AtomicUsize,
+}
+
+// Allocations only ever hand out disjoint parts of the buffer.
+unsafe impl Sync for AtomicArena {}
+
+#[allow(clippy::len_without_is_empty)]
+impl AtomicArena {
+    /// Allocates from the rest of `arena`, after anything already allocated in it.
+    pub fn new(arena: Arena) -> Self {
+        let len = AtomicUsize::new(arena.len);
+        Self { arena, len }
+    }
+
+    /// Returns the arena, with everything allocated so far.
+    pub fn into_inner(mut self) -> Arena {
+        let len = *self.len.get_mut();
+
+        // A mapped file's header is kept up to date as allocations are made.
+        self.arena.cur = unsafe { self.arena.buffer.add(len) };
+        self.arena.len = len;
+
+        self.arena
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.arena.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// The address of the first allocation, before alignment.
+    pub fn base(&self) -> *mut u8 {
+        self.arena.buffer
+    }
+
+    pub fn allocate<T>(&self, n: usize) -> Result<*mut MaybeUninit<T>, Error> {
+        self.allocate_pair::<T, ()>(n, 0).map(|(first, _)| first)
+    }
+
+    /// Reserves `n` values of `T` directly followed by `m` values of `U`, as
+    /// `SharedAllocator::allocate_pair`.
+    #[allow(clippy::type_complexity)]
+    pub fn allocate_pair<T, U>(
+        &self,
+        n: usize,
+        m: usize,
+    ) -> Result<(*mut MaybeUninit<T>, *mut MaybeUninit<U>), Error> {
+        let buffer = self.arena.buffer;
+        let mut len = self.len.load(Ordering::Relaxed);
+
+        loop {
+            let first = buffer.wrapping_add(len);
+            let first = first.wrapping_add(first.align_offset(mem::align_of::<T>()));
+            let second = first.wrapping_add(n * mem::size_of::<T>());
+            let second = second.wrapping_add(second.align_offset(mem::align_of::<U>()));
+            let end = second.wrapping_add(m * mem::size_of::<U>());
+
+            let new_len = end as usize - buffer as usize;
+            if new_len > self.arena.capacity {
+                return Err(Error::OutOfMemory);
+            }
+
+            match self
+                .len
+                .compare_exchange_weak(len, new_len, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => {
+                    #[cfg(unix)]
+                    if let Backing::Mapped {
+                        header: Some(header),
+                        ..
+                    } = self.arena.backing
+                    {
+                        // Threads can get here out of order, so the header's length only grows.
+                        unsafe {
+                            let header_len =
+                                &*(ptr::addr_of_mut!((*header).len) as *const AtomicU64);
+                            header_len.fetch_max(new_len as u64, Ordering::Relaxed);
+                        }
+                    }
+
+                    return Ok((first as *mut MaybeUninit<T>, second as *mut MaybeUninit<U>));
+                }
+                Err(current) => len = current,
+            }
+        }
+    }
+}
+
+impl SharedAllocator for AtomicArena {
+    fn allocate<T>(&self, n: usize) -> Result<Option<*mut MaybeUninit<T>>, Error> {
+        AtomicArena::allocate(self, n).map(Some)
+    }
+
+    fn allocate_pair<T, U>(
+        &self,
+        n: usize,
+        m: usize,
+    ) -> Result<Option<(*mut MaybeUninit<T>, *mut MaybeUninit<U>)>, Error> {
+        AtomicArena::allocate_pair(self, n, m).map(Some)
+    }
+
+    fn len(&self) -> usize {
+        AtomicArena::len(self)
+    }
+
+    fn base(&self) -> *mut u8 {
+        AtomicArena::base(self)
+    }
+}
+
+/// The start of an arena file.  Allocations begin on the next page.
+#[cfg(unix)]
+#[repr(C)]
+struct MappedHeader {
+    magic: [u8; 8],
+    /// Where the file was first mapped.
+    address: u64,
+    capacity: u64,
+    len: u64,
+}
+
+#[cfg(unix)]
+const MAPPED_MAGIC: &[u8; 8] = b"PSARENA1";
+
+#[cfg(unix)]
+const HEADER_SIZE: usize = 4096;
+
+#[cfg(unix)]
+fn map(
+    address: *mut u8,
+    len: usize,
+    flags: libc::c_int,
+    fd: libc::c_int,
+) -> Result<*mut u8, Error> {
+    let mapping = unsafe {
+        libc::mmap(
+            address as *mut libc::c_void,
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            flags,
+            fd,
+            0,
+        )
+    };
+
+    if mapping == libc::MAP_FAILED {
+        Err(Error::Io(io::Error::last_os_error()))
+    } else {
+        Ok(mapping as *mut u8)
     }
 }
 
+/// The size of the system's default huge pages.
+#[cfg(target_os = "linux")]
+fn huge_page_size() -> io::Result<usize> {
+    meminfo("Hugepagesize").map(|kilobytes| kilobytes * 1024)
+}
+
+/// A field of `/proc/meminfo`, in the units it's given in.
+#[cfg(target_os = "linux")]
+fn meminfo(field: &str) -> io::Result<usize> {
+    std::fs::read_to_string("/proc/meminfo")?
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| *name == field)
+        .and_then(|(_, value)| value.split_whitespace().next()?.parse().ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no {} in /proc/meminfo", field),
+            )
+        })
+}
+
 #[derive(Debug)]
 pub enum Error {
     OutOfMemory,
+    /// A mapped arena couldn't be placed at the address it needs.
+    AddressUnavailable,
+    Io(io::Error),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            Error::Io(error) => write!(f, "{}", error),
+            _ => write!(f, "{:?}", self),
+        }
     }
 }
 
 impl std::error::Error for Error {}
 
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
 pub struct DummyArena {
     cur: *mut u8,
     capacity: Option<usize>,
@@ -120,3 +627,212 @@ impl DummyArena {
         }
     }
 }
+
+impl Allocator for DummyArena {
+    fn allocate<T>(&mut self, n: usize) -> Result<Option<*mut MaybeUninit<T>>, Error> {
+        DummyArena::allocate::<T>(self, n).map(|_| None)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn base(&self) -> *mut u8 {
+        ptr::null_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heap_arena() {
+        let mut arena = Arena::with_capacity(16);
+
+        let a = arena.allocate::<u8>(1).unwrap();
+        let b = arena.allocate::<u32>(2).unwrap();
+
+        assert_eq!(a as *mut u8, arena.base());
+        assert_eq!(b as usize % mem::align_of::<u32>(), 0);
+        assert!(arena.len() <= 12);
+        assert!(matches!(arena.allocate::<u64>(2), Err(Error::OutOfMemory)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_anonymous_arena() {
+        let mut arena = Arena::anonymous(1 << 30, false).unwrap();
+
+        let values = arena.allocate::<u64>(1000).unwrap() as *mut u64;
+        unsafe {
+            for i in 0..1000 {
+                values.add(i).write(i as u64);
+            }
+            assert_eq!(values.add(999).read(), 999);
+        }
+
+        assert_eq!(arena.len(), 8000);
+        assert_eq!(arena.capacity(), 1 << 30);
+        arena.flush().unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_huge_page_arena() {
+        if meminfo("HugePages_Free").unwrap_or(0) == 0 {
+            eprintln!("skipping test_huge_page_arena: no huge pages are reserved");
+            return;
+        }
+
+        // Less than a huge page, which has to be unmapped as a whole one.
+        let mut arena = Arena::anonymous(1000, true).unwrap();
+
+        let values = arena.allocate::<u64>(100).unwrap() as *mut u64;
+        unsafe {
+            values.write(1);
+            values.add(99).write(2);
+            assert_eq!(values.read() + values.add(99).read(), 3);
+        }
+
+        assert_eq!(arena.capacity(), 1000);
+        assert!(matches!(
+            arena.allocate::<u8>(1000),
+            Err(Error::OutOfMemory)
+        ));
+
+        match arena.backing {
+            Backing::Mapped { mapping_len, .. } => {
+                assert_eq!(mapping_len, huge_page_size().unwrap())
+            }
+            _ => panic!("arena isn't mapped"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_arena() {
+        let path = std::env::temp_dir().join(format!("arena-{}", std::process::id()));
+
+        let (values, len) = {
+            let mut arena = Arena::create_file(&path, 1 << 20).unwrap();
+
+            arena.allocate::<u8>(3).unwrap();
+            let values = arena.allocate::<u32>(4).unwrap() as *mut u32;
+            unsafe {
+                for i in 0..4 {
+                    values.add(i).write(i as u32 + 10);
+                }
+            }
+
+            arena.flush().unwrap();
+            (values, arena.len())
+        };
+
+        {
+            let mut arena = Arena::open_file(&path).unwrap();
+
+            assert_eq!(arena.len(), len);
+            assert_eq!(arena.capacity(), 1 << 20);
+
+            // Everything is where it was.
+            unsafe {
+                assert_eq!(std::slice::from_raw_parts(values, 4), &[10, 11, 12, 13]);
+            }
+
+            // New allocations come after the old ones.
+            let next = arena.allocate::<u32>(1).unwrap() as *mut u32;
+            assert_eq!(next, unsafe { values.add(4) });
+
+            // The address is taken while the arena is open.
+            assert!(matches!(
+                Arena::open_file(&path),
+                Err(Error::AddressUnavailable)
+            ));
+        }
+
+        std::fs::write(&path, b"not an arena").unwrap();
+        assert!(matches!(Arena::open_file(&path), Err(Error::Io(_))));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_atomic_file_arena() {
+        let path = std::env::temp_dir().join(format!("atomic-arena-{}", std::process::id()));
+
+        let len = {
+            let arena = AtomicArena::new(Arena::create_file(&path, 1 << 20).unwrap());
+
+            arena.allocate::<u8>(3).unwrap();
+            arena.allocate_pair::<u32, u64>(4, 2).unwrap();
+
+            // Dropped without `into_inner`, as when a solve is interrupted.
+            arena.len()
+        };
+
+        let arena = Arena::open_file(&path).unwrap();
+        assert_eq!(arena.len(), len);
+
+        drop(arena);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_atomic_arena() {
+        let mut arena = Arena::with_capacity(1 << 16);
+        arena.allocate::<u8>(1).unwrap();
+
+        let arena = AtomicArena::new(arena);
+
+        let blocks = std::thread::scope(|scope| {
+            let handles = (0..4)
+                .map(|_| {
+                    let arena = &arena;
+                    scope.spawn(move || {
+                        (0..100)
+                            .map(|_| {
+                                let (nodes, values) = arena.allocate_pair::<u8, u64>(3, 2).unwrap();
+                                (nodes as usize, values as usize)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        let mut blocks = blocks;
+        blocks.sort_unstable();
+
+        let base = arena.base() as usize;
+        assert!(blocks[0].0 > base, "allocated over the existing allocation");
+
+        for (nodes, values) in &blocks {
+            assert_eq!(values % mem::align_of::<u64>(), 0);
+            assert!(*values >= nodes + 3 && *values < nodes + 3 + mem::align_of::<u64>());
+        }
+
+        for pair in blocks.windows(2) {
+            assert!(
+                pair[0].1 + 2 * mem::size_of::<u64>() <= pair[1].0,
+                "blocks overlap"
+            );
+        }
+
+        let end = blocks.last().unwrap().1 + 2 * mem::size_of::<u64>();
+        let mut arena = arena.into_inner();
+        assert_eq!(arena.len(), end - base);
+
+        let next = arena.allocate::<u8>(1).unwrap() as usize;
+        assert_eq!(next, end);
+
+        let arena = AtomicArena::new(Arena::with_capacity(8));
+        assert!(matches!(arena.allocate::<u64>(2), Err(Error::OutOfMemory)));
+    }
+}
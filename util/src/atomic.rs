@@ -0,0 +1,44 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// An `f32` that can be shared between threads.  Reads and writes are relaxed: they never tear,
+/// but impose no ordering on other memory, and a read followed by a write is not atomic, so
+/// concurrent updates of the same value can be lost.
+#[repr(transparent)]
+pub struct AtomicF32(AtomicU32);
+
+impl AtomicF32 {
+    pub fn new(value: f32) -> Self {
+        Self(AtomicU32::new(value.to_bits()))
+    }
+
+    pub fn read(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn write(&self, value: f32) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl fmt::Debug for AtomicF32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.read().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_atomic_f32() {
+        // Zeroed memory is 0.0, which parameter initialization relies on.
+        let value: AtomicF32 = unsafe { std::mem::zeroed() };
+        assert_eq!(value.read(), 0.0);
+
+        value.write(-1.5);
+        assert_eq!(value.read(), -1.5);
+        assert_eq!(format!("{:?}", value), "-1.5");
+    }
+}
@@ -1,4 +1,5 @@
 pub mod arena;
+pub mod atomic;
 pub mod math;
 pub mod rng;
 pub mod volatile;